@@ -0,0 +1,13 @@
+-- Every article the agent has seen, keyed by canonical URL.
+CREATE TABLE IF NOT EXISTS articles (
+    url TEXT PRIMARY KEY,
+    content_hash TEXT NOT NULL,
+    title TEXT NOT NULL,
+    source TEXT NOT NULL,
+    published_at TIMESTAMPTZ NOT NULL,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    status TEXT NOT NULL DEFAULT 'new',
+    article JSONB NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS articles_status_idx ON articles (status, first_seen_at);
@@ -0,0 +1,13 @@
+-- Every article the agent has seen, keyed by canonical URL.
+CREATE TABLE IF NOT EXISTS articles (
+    url TEXT PRIMARY KEY NOT NULL,
+    content_hash TEXT NOT NULL,
+    title TEXT NOT NULL,
+    source TEXT NOT NULL,
+    published_at TEXT NOT NULL,
+    first_seen_at TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'new',
+    article TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS articles_status_idx ON articles (status, first_seen_at);
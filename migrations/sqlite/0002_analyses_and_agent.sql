@@ -0,0 +1,22 @@
+-- Sentiment analyses, with the model and prompt that produced them.
+CREATE TABLE IF NOT EXISTS analyses (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    article_url TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    sentiment TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    analyzed_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS analyses_article_url_idx ON analyses (article_url, analyzed_at);
+
+-- Snapshots of agent state and market sentiment.
+CREATE TABLE IF NOT EXISTS agent (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent TEXT NOT NULL,
+    state TEXT NOT NULL,
+    taken_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS agent_taken_at_idx ON agent (agent, taken_at);
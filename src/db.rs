@@ -0,0 +1,3 @@
+pub mod base;
+pub mod postgres;
+pub mod sqlite;
\ No newline at end of file
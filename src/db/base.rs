@@ -0,0 +1,328 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use crate::ai::base::{Sentiment, SentimentAnalysisResult};
+use crate::feeds::base::{canonical_url, Article};
+
+/// Where an article is in the agent's pipeline.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingStatus {
+    /// Seen but not handled yet.
+    #[default]
+    New,
+    Processing,
+    Processed,
+    Failed,
+    /// Deliberately not handled, e.g. irrelevant to any watched market.
+    Skipped,
+}
+
+impl ProcessingStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProcessingStatus::New => "new",
+            ProcessingStatus::Processing => "processing",
+            ProcessingStatus::Processed => "processed",
+            ProcessingStatus::Failed => "failed",
+            ProcessingStatus::Skipped => "skipped",
+        }
+    }
+}
+
+impl fmt::Display for ProcessingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ProcessingStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "new" => Ok(ProcessingStatus::New),
+            "processing" => Ok(ProcessingStatus::Processing),
+            "processed" => Ok(ProcessingStatus::Processed),
+            "failed" => Ok(ProcessingStatus::Failed),
+            "skipped" => Ok(ProcessingStatus::Skipped),
+            other => Err(format!("Unknown processing status: {}", other)),
+        }
+    }
+}
+
+/// What [`ArticleStore::record`] found out about an article.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordOutcome {
+    /// First time this URL is seen.
+    New,
+    /// Same URL and same content as before.
+    Unchanged,
+    /// Same URL but the title or body changed, e.g. an updated story. The stored
+    /// content is replaced and the status goes back to [`ProcessingStatus::New`].
+    Changed,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredArticle {
+    /// Canonical URL, see [`canonical_url`].
+    pub url: String,
+    pub content_hash: String,
+    pub first_seen_at: DateTime<Utc>,
+    pub status: ProcessingStatus,
+    pub article: Article,
+}
+
+/// A sentiment analysis together with what produced it, so results from different
+/// models or prompt revisions can be told apart.
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// Canonical URL of the analyzed article.
+    pub article_url: String,
+    pub model: String,
+    pub prompt: String,
+    pub result: SentimentAnalysisResult,
+    pub analyzed_at: DateTime<Utc>,
+}
+
+impl Analysis {
+    pub fn new(article: &Article, model: &str, prompt: &str, result: SentimentAnalysisResult) -> Self {
+        Self {
+            article_url: article_key(article),
+            model: model.to_string(),
+            prompt: prompt.to_string(),
+            result,
+            analyzed_at: Utc::now(),
+        }
+    }
+}
+
+/// Point-in-time copy of an agent's state, e.g. positions and market sentiment.
+#[derive(Debug, Clone)]
+pub struct AgentSnapshot {
+    pub agent: String,
+    pub state: Value,
+    pub taken_at: DateTime<Utc>,
+}
+
+impl AgentSnapshot {
+    pub fn new(agent: &str, state: Value) -> Self {
+        Self { agent: agent.to_string(), state, taken_at: Utc::now() }
+    }
+}
+
+pub(crate) fn sentiment_name(sentiment: &Sentiment) -> &'static str {
+    match sentiment {
+        Sentiment::Positive => "positive",
+        Sentiment::Negative => "negative",
+        Sentiment::Neutral => "neutral",
+    }
+}
+
+pub(crate) fn parse_sentiment(name: &str) -> Result<Sentiment, String> {
+    match name {
+        "positive" => Ok(Sentiment::Positive),
+        "negative" => Ok(Sentiment::Negative),
+        "neutral" => Ok(Sentiment::Neutral),
+        other => Err(format!("Unknown sentiment: {}", other)),
+    }
+}
+
+/// Key under which an article is stored.
+pub fn article_key(article: &Article) -> String {
+    canonical_url(&article.url)
+}
+
+/// Hex SHA-256 of the title and body, used to tell updated stories from re-deliveries.
+pub fn content_hash(article: &Article) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(article.title.trim().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(article.body.trim().as_bytes());
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Persistent record of the articles the agent has seen, so restarts neither
+/// re-trade old news nor lose track of what is still waiting to be processed.
+#[async_trait]
+pub trait ArticleStore: Send + Sync {
+    /// Stores the article if its URL is new or its content changed.
+    async fn record(&self, article: &Article) -> Result<RecordOutcome, Box<dyn std::error::Error>>;
+
+    async fn get(&self, url: &str) -> Result<Option<StoredArticle>, Box<dyn std::error::Error>>;
+
+    async fn set_status(&self, url: &str, status: ProcessingStatus) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Articles with the given status, oldest first.
+    async fn with_status(
+        &self,
+        status: ProcessingStatus,
+        limit: usize,
+    ) -> Result<Vec<StoredArticle>, Box<dyn std::error::Error>>;
+}
+
+/// Everything the agent persists: articles, the analyses run on them and snapshots of
+/// its own state. Implemented for PostgreSQL in production and SQLite for local runs.
+#[async_trait]
+pub trait Storage: ArticleStore {
+    /// Returns the id of the stored analysis.
+    async fn save_analysis(&self, analysis: &Analysis) -> Result<i64, Box<dyn std::error::Error>>;
+
+    /// Analyses of an article, oldest first.
+    async fn analyses(&self, article_url: &str) -> Result<Vec<Analysis>, Box<dyn std::error::Error>>;
+
+    /// Returns the id of the stored snapshot.
+    async fn save_agent_state(&self, snapshot: &AgentSnapshot) -> Result<i64, Box<dyn std::error::Error>>;
+
+    async fn latest_agent_state(&self, agent: &str) -> Result<Option<AgentSnapshot>, Box<dyn std::error::Error>>;
+}
+
+#[async_trait]
+impl<S: ArticleStore + ?Sized> ArticleStore for Arc<S> {
+    async fn record(&self, article: &Article) -> Result<RecordOutcome, Box<dyn std::error::Error>> {
+        (**self).record(article).await
+    }
+
+    async fn get(&self, url: &str) -> Result<Option<StoredArticle>, Box<dyn std::error::Error>> {
+        (**self).get(url).await
+    }
+
+    async fn set_status(&self, url: &str, status: ProcessingStatus) -> Result<(), Box<dyn std::error::Error>> {
+        (**self).set_status(url, status).await
+    }
+
+    async fn with_status(
+        &self,
+        status: ProcessingStatus,
+        limit: usize,
+    ) -> Result<Vec<StoredArticle>, Box<dyn std::error::Error>> {
+        (**self).with_status(status, limit).await
+    }
+}
+
+#[async_trait]
+impl<S: Storage + ?Sized> Storage for Arc<S> {
+    async fn save_analysis(&self, analysis: &Analysis) -> Result<i64, Box<dyn std::error::Error>> {
+        (**self).save_analysis(analysis).await
+    }
+
+    async fn analyses(&self, article_url: &str) -> Result<Vec<Analysis>, Box<dyn std::error::Error>> {
+        (**self).analyses(article_url).await
+    }
+
+    async fn save_agent_state(&self, snapshot: &AgentSnapshot) -> Result<i64, Box<dyn std::error::Error>> {
+        (**self).save_agent_state(snapshot).await
+    }
+
+    async fn latest_agent_state(&self, agent: &str) -> Result<Option<AgentSnapshot>, Box<dyn std::error::Error>> {
+        (**self).latest_agent_state(agent).await
+    }
+}
+
+/// Checks every backend has to pass, run against each of them.
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// An article under a URL no other test run uses, so a shared database can be reused.
+    pub(crate) fn unique_article(body: &str) -> Article {
+        let url = format!("https://example.com/news/{:016x}", rand::random::<u64>());
+        Article {
+            id: crate::feeds::base::article_id(&url),
+            title: "Fed Holds Rates Steady".to_string(),
+            author: "Jane Doe".to_string(),
+            body: body.to_string(),
+            url,
+            source: "Example".to_string(),
+            published_at: "2024-12-18T19:00:00Z".parse().unwrap(),
+            tags: vec!["Economy".to_string()],
+            tickers: vec!["SPY".to_string()],
+            metadata: [("section".to_string(), Value::from("markets"))].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) async fn check_article_store(store: &impl ArticleStore) {
+        let article = unique_article("The Federal Reserve left rates unchanged.");
+        let url = article.url.clone();
+        assert_eq!(store.record(&article).await.unwrap(), RecordOutcome::New);
+        assert_eq!(store.record(&article).await.unwrap(), RecordOutcome::Unchanged);
+
+        // The same story behind a tracking link is the same article.
+        let tracked = Article { url: format!("{}?utm_source=newsletter", url), ..article.clone() };
+        assert_eq!(store.record(&tracked).await.unwrap(), RecordOutcome::Unchanged);
+
+        let stored = store.get(&url).await.unwrap().expect("stored article");
+        assert_eq!(stored.url, canonical_url(&url));
+        assert_eq!(stored.content_hash, content_hash(&article));
+        assert_eq!(stored.status, ProcessingStatus::New);
+        assert_eq!(stored.article.id, article.id);
+        assert_eq!(stored.article.title, article.title);
+        assert_eq!(stored.article.body, article.body);
+        assert_eq!(stored.article.published_at, article.published_at);
+        assert_eq!(stored.article.tickers, article.tickers);
+        assert_eq!(stored.article.metadata, article.metadata);
+
+        store.set_status(&url, ProcessingStatus::Processed).await.unwrap();
+        assert_eq!(store.get(&url).await.unwrap().unwrap().status, ProcessingStatus::Processed);
+
+        let body = "The Federal Reserve left rates unchanged, signaling fewer cuts.".to_string();
+        let changed = Article { body, ..article };
+        assert_eq!(store.record(&changed).await.unwrap(), RecordOutcome::Changed);
+        let stored = store.get(&url).await.unwrap().unwrap();
+        assert_eq!(stored.content_hash, content_hash(&changed));
+        assert_eq!(stored.article.body, changed.body);
+        assert_eq!(stored.status, ProcessingStatus::New);
+
+        let waiting = store.with_status(ProcessingStatus::New, usize::MAX).await.unwrap();
+        assert!(waiting.iter().any(|stored| stored.url == canonical_url(&url)));
+        assert!(store.set_status("https://example.com/never-stored", ProcessingStatus::Failed).await.is_err());
+    }
+
+    pub(crate) async fn check_storage(store: &impl Storage) {
+        let article = unique_article("The Federal Reserve left rates unchanged.");
+        store.record(&article).await.unwrap();
+
+        let positive = SentimentAnalysisResult { sentiment: Sentiment::Positive, confidence: 0.75 };
+        let first = Analysis::new(&article, "gpt-4o-mini", "v1", positive);
+        let negative = SentimentAnalysisResult { sentiment: Sentiment::Negative, confidence: 0.5 };
+        // Analyzed through a tracking link, still filed under the article.
+        let tracked = Article { url: format!("{}?utm_medium=email", article.url), ..article.clone() };
+        let second = Analysis::new(&tracked, "gpt-4o", "v2", negative);
+        let first_id = store.save_analysis(&first).await.unwrap();
+        let second_id = store.save_analysis(&second).await.unwrap();
+        assert_ne!(first_id, second_id);
+
+        let analyses = store.analyses(&article.url).await.unwrap();
+        assert_eq!(analyses.len(), 2);
+        assert_eq!(analyses[0].article_url, canonical_url(&article.url));
+        assert_eq!((analyses[0].model.as_str(), analyses[0].prompt.as_str()), ("gpt-4o-mini", "v1"));
+        assert_eq!(sentiment_name(&analyses[0].result.sentiment), "positive");
+        assert_eq!(analyses[0].result.confidence, 0.75);
+        assert_eq!(analyses[1].model, "gpt-4o");
+        assert_eq!(sentiment_name(&analyses[1].result.sentiment), "negative");
+
+        let agent = format!("agent-{:016x}", rand::random::<u64>());
+        assert!(store.latest_agent_state(&agent).await.unwrap().is_none());
+        let older = AgentSnapshot::new(&agent, serde_json::json!({"positions": {"SOL": 12.5}, "sentiment": "neutral"}));
+        let newer = AgentSnapshot {
+            taken_at: older.taken_at + chrono::Duration::seconds(1),
+            ..AgentSnapshot::new(&agent, serde_json::json!({"positions": {}, "sentiment": "negative"}))
+        };
+        store.save_agent_state(&older).await.unwrap();
+        store.save_agent_state(&newer).await.unwrap();
+
+        let latest = store.latest_agent_state(&agent).await.unwrap().expect("saved snapshot");
+        assert_eq!(latest.agent, agent);
+        assert_eq!(latest.state, newer.state);
+    }
+}
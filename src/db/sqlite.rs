@@ -0,0 +1,244 @@
+use std::path::Path;
+use std::str::FromStr;
+use async_trait::async_trait;
+use chrono::Utc;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::types::Json;
+use sqlx::Row;
+use crate::ai::base::SentimentAnalysisResult;
+use crate::feeds::base::{canonical_url, Article};
+use super::base::{
+    article_key, content_hash, parse_sentiment, sentiment_name, AgentSnapshot, Analysis, ArticleStore,
+    ProcessingStatus, RecordOutcome, Storage, StoredArticle,
+};
+
+/// Embedded [`Storage`] kept in a single SQLite file.
+#[derive(Clone)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it and its tables if needed.
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        Self::connect_with(SqlitePoolOptions::new().connect_with(options).await?).await
+    }
+
+    /// A throwaway database that lives as long as the store, for dry runs.
+    pub async fn in_memory() -> Result<Self, Box<dyn std::error::Error>> {
+        // Every connection to `:memory:` gets its own database, so keep exactly one.
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(SqliteConnectOptions::from_str("sqlite::memory:")?)
+            .await?;
+        Self::connect_with(pool).await
+    }
+
+    async fn connect_with(pool: SqlitePool) -> Result<Self, Box<dyn std::error::Error>> {
+        sqlx::migrate!("migrations/sqlite").run(&pool).await?;
+        Ok(Self { pool })
+    }
+
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+}
+
+fn stored_article(row: SqliteRow) -> Result<StoredArticle, Box<dyn std::error::Error>> {
+    let status: String = row.try_get("status")?;
+    let article: String = row.try_get("article")?;
+    Ok(StoredArticle {
+        url: row.try_get("url")?,
+        content_hash: row.try_get("content_hash")?,
+        first_seen_at: row.try_get("first_seen_at")?,
+        status: status.parse()?,
+        article: serde_json::from_str(&article)?,
+    })
+}
+
+fn analysis(row: SqliteRow) -> Result<Analysis, Box<dyn std::error::Error>> {
+    let sentiment: String = row.try_get("sentiment")?;
+    Ok(Analysis {
+        article_url: row.try_get("article_url")?,
+        model: row.try_get("model")?,
+        prompt: row.try_get("prompt")?,
+        result: SentimentAnalysisResult {
+            sentiment: parse_sentiment(&sentiment)?,
+            confidence: row.try_get("confidence")?,
+        },
+        analyzed_at: row.try_get("analyzed_at")?,
+    })
+}
+
+#[async_trait]
+impl ArticleStore for SqliteStore {
+    async fn record(&self, article: &Article) -> Result<RecordOutcome, Box<dyn std::error::Error>> {
+        let url = article_key(article);
+        let hash = content_hash(article);
+        let json = serde_json::to_string(article)?;
+
+        let inserted = sqlx::query(
+            "INSERT INTO articles (url, content_hash, title, source, published_at, first_seen_at, status, article)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (url) DO NOTHING",
+        )
+        .bind(&url)
+        .bind(&hash)
+        .bind(&article.title)
+        .bind(&article.source)
+        .bind(article.published_at)
+        .bind(Utc::now())
+        .bind(ProcessingStatus::New.as_str())
+        .bind(&json)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        if inserted > 0 {
+            return Ok(RecordOutcome::New);
+        }
+
+        let updated = sqlx::query(
+            "UPDATE articles
+             SET content_hash = ?, title = ?, source = ?, published_at = ?, status = ?, article = ?
+             WHERE url = ? AND content_hash <> ?",
+        )
+        .bind(&hash)
+        .bind(&article.title)
+        .bind(&article.source)
+        .bind(article.published_at)
+        .bind(ProcessingStatus::New.as_str())
+        .bind(&json)
+        .bind(&url)
+        .bind(&hash)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(if updated > 0 { RecordOutcome::Changed } else { RecordOutcome::Unchanged })
+    }
+
+    async fn get(&self, url: &str) -> Result<Option<StoredArticle>, Box<dyn std::error::Error>> {
+        let row = sqlx::query("SELECT * FROM articles WHERE url = ?")
+            .bind(canonical_url(url))
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(stored_article).transpose()
+    }
+
+    async fn set_status(&self, url: &str, status: ProcessingStatus) -> Result<(), Box<dyn std::error::Error>> {
+        let updated = sqlx::query("UPDATE articles SET status = ? WHERE url = ?")
+            .bind(status.as_str())
+            .bind(canonical_url(url))
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        if updated == 0 {
+            return Err(format!("No stored article for {}", url).into());
+        }
+        Ok(())
+    }
+
+    async fn with_status(
+        &self,
+        status: ProcessingStatus,
+        limit: usize,
+    ) -> Result<Vec<StoredArticle>, Box<dyn std::error::Error>> {
+        let rows = sqlx::query("SELECT * FROM articles WHERE status = ? ORDER BY first_seen_at LIMIT ?")
+            .bind(status.as_str())
+            .bind(limit.min(i64::MAX as usize) as i64)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.into_iter().map(stored_article).collect()
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStore {
+    async fn save_analysis(&self, analysis: &Analysis) -> Result<i64, Box<dyn std::error::Error>> {
+        let id = sqlx::query_scalar(
+            "INSERT INTO analyses (article_url, model, prompt, sentiment, confidence, analyzed_at)
+             VALUES (?, ?, ?, ?, ?, ?)
+             RETURNING id",
+        )
+        .bind(canonical_url(&analysis.article_url))
+        .bind(&analysis.model)
+        .bind(&analysis.prompt)
+        .bind(sentiment_name(&analysis.result.sentiment))
+        .bind(analysis.result.confidence)
+        .bind(analysis.analyzed_at)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(id)
+    }
+
+    async fn analyses(&self, article_url: &str) -> Result<Vec<Analysis>, Box<dyn std::error::Error>> {
+        let rows = sqlx::query("SELECT * FROM analyses WHERE article_url = ? ORDER BY analyzed_at, id")
+            .bind(canonical_url(article_url))
+            .fetch_all(&self.pool)
+            .await?;
+        rows.into_iter().map(analysis).collect()
+    }
+
+    async fn save_agent_state(&self, snapshot: &AgentSnapshot) -> Result<i64, Box<dyn std::error::Error>> {
+        let id = sqlx::query_scalar("INSERT INTO agent (agent, state, taken_at) VALUES (?, ?, ?) RETURNING id")
+            .bind(&snapshot.agent)
+            .bind(Json(&snapshot.state))
+            .bind(snapshot.taken_at)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(id)
+    }
+
+    async fn latest_agent_state(&self, agent: &str) -> Result<Option<AgentSnapshot>, Box<dyn std::error::Error>> {
+        let row = sqlx::query("SELECT * FROM agent WHERE agent = ? ORDER BY taken_at DESC, id DESC LIMIT 1")
+            .bind(agent)
+            .fetch_optional(&self.pool)
+            .await?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let Json(state) = row.try_get("state")?;
+        Ok(Some(AgentSnapshot {
+            agent: row.try_get("agent")?,
+            state,
+            taken_at: row.try_get("taken_at")?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::base::tests::{check_article_store, check_storage, unique_article};
+
+    #[tokio::test]
+    async fn article_store() {
+        check_article_store(&SqliteStore::in_memory().await.unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn storage() {
+        check_storage(&SqliteStore::in_memory().await.unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn articles_survive_reopening() {
+        let path = std::env::temp_dir().join(format!("bloomy-os-{:016x}.db", rand::random::<u64>()));
+        let article = unique_article("The Federal Reserve left rates unchanged.");
+
+        let store = SqliteStore::open(&path).await.unwrap();
+        assert_eq!(store.record(&article).await.unwrap(), RecordOutcome::New);
+        store.pool().close().await;
+
+        let store = SqliteStore::open(&path).await.unwrap();
+        assert_eq!(store.record(&article).await.unwrap(), RecordOutcome::Unchanged);
+        store.pool().close().await;
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,2 +1,20 @@
 pub mod base;
-pub mod bloomberg; 
\ No newline at end of file
+pub mod bloomberg;
+pub mod calendar;
+pub mod cluster;
+pub mod edgar;
+pub mod error;
+pub mod exec;
+pub mod extract;
+pub mod http;
+pub mod multi;
+pub mod replay;
+pub mod rss;
+pub mod sitemap;
+pub mod solana;
+pub mod tagger;
+pub mod unseen;
+pub mod webhook;
+mod xml;
+#[cfg(test)]
+mod test_util;
@@ -1,18 +1,571 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
 use async_trait::async_trait;
-use serde::{Serialize, Deserialize};
+use futures::future::BoxFuture;
+use futures::Stream;
+use rand::Rng;
+use serde::{Deserializer, Serialize, Serializer, Deserialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use chrono::{DateTime, Utc};
+use tokio::sync::mpsc;
+use tokio::task::{AbortHandle, JoinHandle};
+use super::error::FeedError;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How much of the story made it into `Article::body`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BodyStatus {
+    /// The complete story text.
+    #[default]
+    Full,
+    /// Only part of the story, e.g. a teaser or the first paragraphs.
+    Truncated,
+    /// The publisher withheld the story; the body holds whatever was visible.
+    Paywalled,
+    /// No story text was found; the body falls back to the summary.
+    Missing,
+    /// Only the headline is known so far. A version with the body and the same id follows.
+    Pending,
+}
+
+impl BodyStatus {
+    pub fn is_full(&self) -> bool {
+        *self == BodyStatus::Full
+    }
+}
+
+// `remote = "Self"` turns the derives into inherent functions wrapped by the impls below,
+// which fill in the id of articles serialized before ids existed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(remote = "Self")]
 pub struct Article {
+    /// Stable identifier derived from the canonical URL, see [`article_id`].
+    #[serde(default)]
+    pub id: String,
     pub title: String,
     pub author: String,
     pub body: String,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub body_status: BodyStatus,
     pub url: String,
     pub source: String,
+    /// Name of the feed that delivered the article when several feeds are combined.
+    #[serde(default)]
+    pub origin: Option<String>,
     pub published_at: DateTime<Utc>,
+    /// When the article was fetched, as opposed to published. Unknown for articles
+    /// recorded before it was tracked.
+    #[serde(default)]
+    pub fetched_at: Option<DateTime<Utc>>,
+    /// Language code such as `en` or `en-US`, when the source declares one.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Publisher categories and keywords.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Ticker or coin symbols the article is about, e.g. `AAPL` or `BTC`.
+    #[serde(default)]
+    pub tickers: Vec<String>,
+    /// Anything else a source knows about the article, e.g. an EDGAR accession number.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, Value>,
+}
+
+impl Serialize for Article {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Article::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Article {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut article = Article::deserialize(deserializer)?;
+        if article.id.is_empty() && !article.url.is_empty() {
+            article.id = article_id(&article.url);
+        }
+        Ok(article)
+    }
+}
+
+/// Key in [`Article::metadata`] set to `true` when the source gave no publication time
+/// and `published_at` holds the time the article was fetched instead.
+pub const PUBLISHED_AT_ESTIMATED: &str = "published_at_estimated";
+
+impl Article {
+    /// Sets the publication time the source gave, or, without one, the fetch time
+    /// flagged with [`PUBLISHED_AT_ESTIMATED`].
+    pub fn set_published_at(&mut self, published_at: Option<DateTime<Utc>>) {
+        match published_at {
+            Some(published_at) => {
+                self.published_at = published_at;
+                self.metadata.remove(PUBLISHED_AT_ESTIMATED);
+            }
+            None => {
+                self.published_at = *self.fetched_at.get_or_insert_with(Utc::now);
+                self.metadata.insert(PUBLISHED_AT_ESTIMATED.to_string(), Value::Bool(true));
+            }
+        }
+    }
+
+    /// Whether `published_at` is only the fetch time, see [`Article::set_published_at`].
+    pub fn published_at_is_estimated(&self) -> bool {
+        self.metadata.get(PUBLISHED_AT_ESTIMATED).and_then(Value::as_bool).unwrap_or(false)
+    }
+}
+
+/// Identifier of the story at `url`: the first 16 hex digits of the SHA-256 of its
+/// canonical URL, so the same story gets the same id whichever link it came from.
+pub fn article_id(url: &str) -> String {
+    Sha256::digest(canonical_url(url).as_bytes())
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// A story or page that could not be fetched or parsed.
+#[derive(Debug)]
+pub struct StoryError {
+    pub url: String,
+    /// Why, e.g. [`FeedError::Blocked`] for a robot check served instead of the story.
+    pub error: FeedError,
+}
+
+impl StoryError {
+    pub fn new(url: &str, error: FeedError) -> Self {
+        Self { url: url.to_string(), error }
+    }
+
+    /// Keeps a boxed [`FeedError`] as it is; any other error counts as a parse failure.
+    pub fn from_boxed(url: &str, error: Box<dyn std::error::Error>) -> Self {
+        match error.downcast::<FeedError>() {
+            Ok(error) => Self::new(url, *error),
+            Err(error) => Self::new(url, FeedError::parse(url, error)),
+        }
+    }
+}
+
+impl fmt::Display for StoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            FeedError::Request(e) => write!(f, "{}: {}", self.url, e),
+            error => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for StoryError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Outcome of one poll: the new articles in publication order and the stories that failed.
+#[derive(Debug, Default)]
+pub struct FetchReport {
+    pub articles: Vec<Article>,
+    pub failures: Vec<StoryError>,
+}
+
+// Query parameters that only track where a reader came from.
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "cmpid", "srnd", "ref", "taid", "leadsource", "sref"];
+
+/// Normalizes a URL so the same story linked from different places compares equal:
+/// the scheme becomes https, `www.`, fragments, tracking parameters and trailing
+/// slashes are dropped.
+pub fn canonical_url(url: &str) -> String {
+    let Ok(mut parsed) = reqwest::Url::parse(url.trim()) else {
+        return url.trim().to_lowercase();
+    };
+
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(key, _)| {
+            let key = key.to_lowercase();
+            !key.starts_with("utm_") && !TRACKING_PARAMS.contains(&key.as_str())
+        })
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if pairs.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    parsed.set_fragment(None);
+
+    let host = parsed.host_str().unwrap_or_default();
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let path = parsed.path().trim_end_matches('/');
+    match parsed.query() {
+        Some(query) => format!("https://{}{}?{}", host, path, query),
+        None => format!("https://{}{}", host, path),
+    }
+}
+
+/// Lowercases a headline and keeps only its words, so punctuation and spacing
+/// differences between publishers do not matter.
+pub fn normalized_title(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 #[async_trait]
-pub trait Feed {
+pub trait Feed: Send + Sync {
     async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>>;
+
+    /// Like [`Feed::get_new_articles`], but also returns the stories that failed while
+    /// the poll as a whole succeeded. Feeds that fetch stories one by one override it.
+    async fn fetch_report(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        Ok(FetchReport { articles: self.get_new_articles().await?, failures: Vec::new() })
+    }
+
+    /// Polls this feed on a background task and streams every new article.
+    fn subscribe(self, options: SubscribeOptions) -> Subscription
+    where
+        Self: Sized + 'static,
+    {
+        subscribe(self, options)
+    }
+}
+
+#[async_trait]
+impl<F: Feed + ?Sized> Feed for Arc<F> {
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        (**self).get_new_articles().await
+    }
+
+    async fn fetch_report(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        (**self).fetch_report().await
+    }
+}
+
+/// Polling schedule for [`subscribe`].
+#[derive(Debug, Clone)]
+pub struct SubscribeOptions {
+    poll_interval: Duration,
+    jitter: Duration,
+    max_backoff: Duration,
+    buffer: usize,
+}
+
+impl Default for SubscribeOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(60),
+            jitter: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(15 * 60),
+            buffer: 256,
+        }
+    }
+}
+
+impl SubscribeOptions {
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Upper bound of the random delay added to every wait, so feeds sharing a
+    /// schedule do not hit their sources at the same instant.
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Longest wait after repeated errors; the wait doubles with each consecutive error.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Number of articles held before polling pauses for the consumer to catch up.
+    pub fn buffer(mut self, buffer: usize) -> Self {
+        self.buffer = buffer.max(1);
+        self
+    }
+
+    fn delay(&self, consecutive_errors: u32) -> Duration {
+        let base = if consecutive_errors == 0 {
+            self.poll_interval
+        } else {
+            let factor = 2u32.saturating_pow(consecutive_errors.min(16));
+            self.poll_interval.saturating_mul(factor).min(self.max_backoff)
+        };
+        let jitter = if self.jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            rand::thread_rng().gen_range(Duration::ZERO..=self.jitter)
+        };
+        base + jitter
+    }
+}
+
+/// Stream of articles from a feed polled in the background. Dropping the
+/// subscription or calling [`Subscription::cancel`] stops the polling task.
+pub struct Subscription {
+    receiver: mpsc::Receiver<Article>,
+    task: JoinHandle<()>,
+    failures: Arc<Mutex<Failures>>,
+}
+
+/// Failed rounds since the last successful one, shared with the polling task.
+#[derive(Debug, Default)]
+struct Failures {
+    consecutive: u32,
+    last_error: Option<String>,
+}
+
+impl Subscription {
+    pub fn cancel(&self) {
+        self.task.abort();
+    }
+
+    /// Handle that stops the polling task from elsewhere, e.g. a shutdown hook.
+    pub fn cancel_handle(&self) -> AbortHandle {
+        self.task.abort_handle()
+    }
+
+    /// Whether the polling task has stopped, whether cancelled or for any other reason.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+
+    /// Number of polls in a row that failed; 0 once one succeeds.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.failures.lock().unwrap().consecutive
+    }
+
+    /// Why the latest poll failed, while polls keep failing.
+    pub fn last_error(&self) -> Option<String> {
+        self.failures.lock().unwrap().last_error.clone()
+    }
+}
+
+impl Stream for Subscription {
+    type Item = Article;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Turns any feed into a stream of articles. The first poll happens immediately;
+/// errors are retried with exponential backoff. Must be called within a tokio runtime.
+pub fn subscribe<F: Feed + 'static>(feed: F, options: SubscribeOptions) -> Subscription {
+    poll_loop(feed, options, |feed, sender| {
+        Box::pin(async move {
+            // The error is not `Send` and must not live across an await.
+            let articles = feed.get_new_articles().await.map_err(|e| e.to_string());
+            match articles {
+                Ok(articles) => send_all(sender, articles).await,
+                Err(e) => Polled::Failed(e),
+            }
+        })
+    })
+}
+
+/// How one round of a [`poll_loop`] went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Polled {
+    Ok,
+    /// The next round waits longer with every failure in a row. Holds the error, as
+    /// reported by [`Subscription::last_error`].
+    Failed(String),
+    /// The subscription is gone and polling stops.
+    Closed,
+}
+
+/// Calls `poll` on a background task following the schedule in `options`. `poll` sends
+/// the articles itself, so a feed can stream them while the round is still running.
+pub(crate) fn poll_loop<F, P>(feed: F, options: SubscribeOptions, poll: P) -> Subscription
+where
+    F: Send + Sync + 'static,
+    P: for<'a> Fn(&'a F, &'a mpsc::Sender<Article>) -> BoxFuture<'a, Polled> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel(options.buffer);
+    let failures = Arc::new(Mutex::new(Failures::default()));
+
+    let task = {
+        let failures = failures.clone();
+        tokio::spawn(async move {
+            loop {
+                let consecutive_errors = {
+                    let polled = poll(&feed, &sender).await;
+                    let mut failures = failures.lock().unwrap();
+                    match polled {
+                        Polled::Ok => *failures = Failures::default(),
+                        Polled::Failed(error) => {
+                            failures.consecutive += 1;
+                            failures.last_error = Some(error);
+                        }
+                        Polled::Closed => return,
+                    }
+                    failures.consecutive
+                };
+                tokio::time::sleep(options.delay(consecutive_errors)).await;
+            }
+        })
+    };
+
+    Subscription { receiver, task, failures }
+}
+
+pub(crate) async fn send_all(sender: &mpsc::Sender<Article>, articles: Vec<Article>) -> Polled {
+    for article in articles {
+        if sender.send(article).await.is_err() {
+            return Polled::Closed;
+        }
+    }
+    Polled::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashSet, VecDeque};
+    use std::time::Instant;
+    use futures::StreamExt;
+    use super::*;
+
+    /// Answers polls from `results` in order and then with no articles, noting when
+    /// each poll happened.
+    struct Scripted {
+        results: Mutex<VecDeque<Result<Vec<Article>, String>>>,
+        polls: Arc<Mutex<Vec<Instant>>>,
+    }
+
+    impl Scripted {
+        fn new(results: Vec<Result<Vec<Article>, String>>) -> (Self, Arc<Mutex<Vec<Instant>>>) {
+            let polls = Arc::new(Mutex::new(Vec::new()));
+            (Self { results: Mutex::new(results.into()), polls: polls.clone() }, polls)
+        }
+    }
+
+    #[async_trait]
+    impl Feed for Scripted {
+        async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+            self.polls.lock().unwrap().push(Instant::now());
+            Ok(self.results.lock().unwrap().pop_front().unwrap_or(Ok(Vec::new()))?)
+        }
+    }
+
+    fn article(url: &str) -> Article {
+        Article { id: article_id(url), url: url.to_string(), ..Default::default() }
+    }
+
+    /// Waits up to a second for `condition`.
+    async fn eventually(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while !condition() {
+            assert!(Instant::now() < deadline, "condition not met within a second");
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[test]
+    fn old_articles_deserialize_with_an_id() {
+        // The shape articles were stored in before ids, summaries and the like existed.
+        let json = r#"{
+            "title": "Fed Holds Rates Steady",
+            "author": "Jane Doe",
+            "body": "The Federal Reserve held rates.",
+            "url": "https://example.com/fed?utm_source=feed",
+            "source": "Example",
+            "published_at": "2024-12-18T19:00:00Z"
+        }"#;
+        let article: Article = serde_json::from_str(json).unwrap();
+        assert_eq!(article.id, article_id("https://example.com/fed"));
+        assert_eq!(article.title, "Fed Holds Rates Steady");
+        assert_eq!(article.summary, None);
+        assert_eq!(article.body_status, BodyStatus::Full);
+        assert_eq!(article.origin, None);
+        assert_eq!(article.fetched_at, None);
+        assert!(article.tags.is_empty() && article.tickers.is_empty() && article.metadata.is_empty());
+
+        // Ids that were stored are kept, and serializing round-trips.
+        let stored = Article { id: "kept".to_string(), ..article };
+        let round_trip: Article = serde_json::from_str(&serde_json::to_string(&stored).unwrap()).unwrap();
+        assert_eq!(round_trip.id, "kept");
+    }
+
+    #[test]
+    fn delay_doubles_up_to_max_backoff() {
+        let options = SubscribeOptions::default()
+            .poll_interval(Duration::from_secs(1))
+            .jitter(Duration::ZERO)
+            .max_backoff(Duration::from_secs(10));
+        let delays: Vec<_> = [0, 1, 2, 3, 4, 100].iter().map(|&errors| options.delay(errors).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 10, 10]);
+    }
+
+    #[test]
+    fn delay_adds_jitter_within_bounds() {
+        let options = SubscribeOptions::default()
+            .poll_interval(Duration::from_secs(1))
+            .jitter(Duration::from_millis(500));
+        let delays: HashSet<_> = (0..200).map(|_| options.delay(0)).collect();
+        assert!(delays.iter().all(|delay| (Duration::from_secs(1)..=Duration::from_millis(1500)).contains(delay)));
+        assert!(delays.len() > 1, "no jitter added");
+    }
+
+    #[tokio::test]
+    async fn backs_off_on_failures_and_reports_them() {
+        let (feed, polls) = Scripted::new(vec![
+            Err("lineup down".to_string()),
+            Err("still down".to_string()),
+            Ok(vec![article("https://example.com/a")]),
+        ]);
+        let options = SubscribeOptions::default()
+            .poll_interval(Duration::from_millis(50))
+            .jitter(Duration::ZERO)
+            .max_backoff(Duration::from_secs(1));
+        let mut subscription = subscribe(feed, options);
+
+        eventually(|| subscription.consecutive_failures() == 2).await;
+        assert_eq!(subscription.last_error().as_deref(), Some("still down"));
+
+        let received = tokio::time::timeout(Duration::from_secs(2), subscription.next()).await.unwrap().unwrap();
+        assert_eq!(received.url, "https://example.com/a");
+        eventually(|| subscription.consecutive_failures() == 0).await;
+        assert_eq!(subscription.last_error(), None);
+
+        // 100ms after the first failure, 200ms after the second, then the interval again.
+        eventually(|| polls.lock().unwrap().len() >= 4).await;
+        let polls = polls.lock().unwrap();
+        let gaps: Vec<_> = polls.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        assert!(gaps[0] >= Duration::from_millis(100), "{:?}", gaps);
+        assert!(gaps[1] >= Duration::from_millis(200), "{:?}", gaps);
+        assert!(gaps[2] >= Duration::from_millis(50) && gaps[2] < Duration::from_millis(200), "{:?}", gaps);
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_polling() {
+        let (feed, polls) = Scripted::new(Vec::new());
+        let options = SubscribeOptions::default().poll_interval(Duration::from_millis(10)).jitter(Duration::ZERO);
+        let mut subscription = subscribe(feed, options);
+        eventually(|| polls.lock().unwrap().len() >= 2).await;
+        assert!(!subscription.is_finished());
+
+        subscription.cancel();
+        eventually(|| subscription.is_finished()).await;
+        let count = polls.lock().unwrap().len();
+        assert!(subscription.next().await.is_none());
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(polls.lock().unwrap().len(), count);
+    }
 }
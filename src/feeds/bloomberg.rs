@@ -1,37 +1,663 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use futures::stream::{self, Stream, StreamExt};
 use reqwest;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
 use chrono::{DateTime, Utc};
-use super::base::Article;
-use super::base::Feed;
+use super::base::{article_id, Article};
+use super::base::BodyStatus;
+use super::base::{poll_loop, send_all, subscribe, Feed, Polled, SubscribeOptions, Subscription};
+use super::base::{FetchReport, StoryError};
+use super::error::FeedError;
+use super::extract;
+use super::http::{self, CacheStats, HttpClient, RetryPolicy};
 use scraper::Html;
 use async_trait;
 
-#[derive(Debug, Deserialize)]
+/// A story as listed by the lineup API. Fields not modelled here are kept in `extra`.
+#[derive(Debug, Clone, Deserialize)]
 struct BloombergArticle {
     pub headline: String,
+    #[serde(default)]
     pub byline: String,
     pub url: String,
+    #[serde(alias = "publishedAt")]
     pub published_at: String,
+    #[serde(default, alias = "updatedAt", deserialize_with = "lenient_text")]
+    pub updated_at: Option<String>,
+    #[serde(default, rename = "abstract", alias = "summary", deserialize_with = "lenient_text")]
+    pub summary: Option<String>,
+    #[serde(default, alias = "primaryCategory", deserialize_with = "lenient_text")]
+    pub primary_category: Option<String>,
+    #[serde(default, deserialize_with = "lenient_names")]
+    pub tickers: Vec<String>,
+    #[serde(default, deserialize_with = "lenient_names")]
+    pub tags: Vec<String>,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+    /// Seen before with a different `updated_at`.
+    #[serde(skip)]
+    pub is_update: bool,
+}
+
+const DEFAULT_BASE_URL: &str = "https://www.bloomberg.com";
+// A body that is not clearly longer than the og:description teaser is treated as cut off.
+const MIN_BODY_TO_TEASER_RATIO: usize = 2;
+// Where Bloomberg redirects suspected bots and readers without a subscription.
+const BLOCK_REDIRECTS: &[&str] = &["/tosv2", "/tos.html", "captcha"];
+const PAYWALL_REDIRECTS: &[&str] = &["/subscriptions", "/subscribe", "paywall"];
+// Keys in `Article::metadata` set from the lineup.
+const UPDATED_AT_KEY: &str = "updated_at";
+const IS_UPDATE_KEY: &str = "is_update";
+
+/// Edition requested through the `exp_pref` and `country_code` cookies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Amer,
+    Emea,
+    Apac,
+}
+
+impl Region {
+    fn exp_pref(&self) -> &'static str {
+        match self {
+            Region::Amer => "AMER",
+            Region::Emea => "EMEA",
+            Region::Apac => "APAC",
+        }
+    }
+
+    fn default_country_code(&self) -> &'static str {
+        match self {
+            Region::Amer => "US",
+            Region::Emea => "GB",
+            Region::Apac => "HK",
+        }
+    }
+}
+
+/// Lineup query and request settings for a [`Bloomberg`] feed, e.g.
+/// `BloombergConfig::default().brands(&["TECHNOLOGY", "CRYPTO"]).region(Region::Emea)`.
+#[derive(Debug, Clone)]
+pub struct BloombergConfig {
+    base_url: String,
+    brands: Vec<String>,
+    types: Vec<String>,
+    page_size: usize,
+    max_pages: u32,
+    region: Region,
+    country_code: Option<String>,
+    concurrency: usize,
+    request_timeout: Duration,
+    rate_limit: Duration,
+    retry: RetryPolicy,
+    story_cache: Option<(PathBuf, Duration)>,
+    block_threshold: u32,
+    cool_down: Duration,
+    headlines_first: bool,
+}
+
+impl Default for BloombergConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            brands: vec!["MARKETS".to_string()],
+            types: vec!["ARTICLE".to_string()],
+            page_size: 25,
+            max_pages: 10,
+            region: Region::Amer,
+            country_code: None,
+            concurrency: 8,
+            request_timeout: Duration::from_secs(10),
+            rate_limit: Duration::from_millis(250),
+            retry: RetryPolicy::default(),
+            story_cache: None,
+            block_threshold: 3,
+            cool_down: Duration::from_secs(15 * 60),
+            headlines_first: false,
+        }
+    }
+}
+
+impl BloombergConfig {
+    /// Scheme and host every request is sent to, e.g. a local mock server.
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn brand(self, brand: &str) -> Self {
+        self.brands(&[brand])
+    }
+
+    pub fn brands(mut self, brands: &[&str]) -> Self {
+        self.brands = brands.iter().map(|b| b.to_string()).collect();
+        self
+    }
+
+    pub fn types(mut self, types: &[&str]) -> Self {
+        self.types = types.iter().map(|t| t.to_string()).collect();
+        self
+    }
+
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Upper bound on lineup pages walked in a single poll.
+    pub fn max_pages(mut self, max_pages: u32) -> Self {
+        self.max_pages = max_pages.max(1);
+        self
+    }
+
+    pub fn region(mut self, region: Region) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Overrides the `country_code` cookie, which otherwise follows the region.
+    pub fn country_code(mut self, country_code: &str) -> Self {
+        self.country_code = Some(country_code.to_string());
+        self
+    }
+
+    /// Maximum number of story pages fetched at the same time.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Time allowed for a single request attempt.
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// Minimum time between two requests to Bloomberg.
+    pub fn rate_limit(mut self, min_interval: Duration) -> Self {
+        self.rate_limit = min_interval;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Keeps story pages in `dir` so they are not downloaded again for `ttl`, even
+    /// across restarts. Within a session a story is never downloaded twice regardless.
+    pub fn story_cache<P: Into<PathBuf>>(mut self, dir: P, ttl: Duration) -> Self {
+        self.story_cache = Some((dir.into(), ttl));
+        self
+    }
+
+    /// Number of blocked requests in a row after which the feed stops sending requests
+    /// for the cool-down period.
+    pub fn block_threshold(mut self, block_threshold: u32) -> Self {
+        self.block_threshold = block_threshold.max(1);
+        self
+    }
+
+    pub fn cool_down(mut self, cool_down: Duration) -> Self {
+        self.cool_down = cool_down;
+        self
+    }
+
+    /// Emit a headline-only article ([`BodyStatus::Pending`]) as soon as a story shows
+    /// up in the lineup, followed by the full story under the same id.
+    pub fn headlines_first(mut self, headlines_first: bool) -> Self {
+        self.headlines_first = headlines_first;
+        self
+    }
+
+    fn cookie(&self) -> String {
+        let country_code = self.country_code.as_deref().unwrap_or(self.region.default_country_code());
+        format!("exp_pref={}; country_code={}", self.region.exp_pref(), country_code)
+    }
+
+    fn absolute_url(&self, url: &str) -> String {
+        if url.starts_with('/') {
+            format!("{}{}", self.base_url, url)
+        } else {
+            url.to_string()
+        }
+    }
+}
+
+impl BloombergArticle {
+    fn published_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.published_at)
+            .ok()
+            .map(|date| date.with_timezone(&Utc))
+    }
+
+    /// The article as far as the lineup knows it, before the story page is fetched.
+    fn headline_article(&self) -> Article {
+        let mut article = Article {
+            id: article_id(&self.url),
+            title: self.headline.clone(),
+            author: self.byline.clone(),
+            body_status: BodyStatus::Pending,
+            url: self.url.clone(),
+            source: "Bloomberg".to_string(),
+            fetched_at: Some(Utc::now()),
+            ..Default::default()
+        };
+        article.set_published_at(self.published_at());
+        self.annotate(&mut article);
+        article
+    }
+
+    /// Adds what the lineup knows beyond the story page: the abstract when the page has
+    /// no description, tickers, category and tags, and the remaining fields as metadata.
+    fn annotate(&self, article: &mut Article) {
+        if article.author.is_empty() {
+            article.author = self.byline.clone();
+        }
+        if article.summary.as_deref().is_none_or(str::is_empty) {
+            article.summary = self.summary.clone();
+        }
+        for ticker in &self.tickers {
+            if !article.tickers.contains(ticker) {
+                article.tickers.push(ticker.clone());
+            }
+        }
+        for tag in self.primary_category.iter().chain(&self.tags) {
+            if !article.tags.contains(tag) {
+                article.tags.push(tag.clone());
+            }
+        }
+
+        let metadata = &mut article.metadata;
+        if let Some(updated_at) = &self.updated_at {
+            metadata.insert(UPDATED_AT_KEY.to_string(), Value::String(updated_at.clone()));
+        }
+        if let Some(category) = &self.primary_category {
+            metadata.insert("primary_category".to_string(), Value::String(category.clone()));
+        }
+        if self.is_update {
+            metadata.insert(IS_UPDATE_KEY.to_string(), Value::Bool(true));
+        }
+        for (key, value) in &self.extra {
+            metadata.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
+
+/// Whether the article is a new version of a story returned before, i.e. the lineup
+/// reported a different `updated_at` for it.
+pub fn is_update(article: &Article) -> bool {
+    article.metadata.get(IS_UPDATE_KEY).and_then(Value::as_bool).unwrap_or(false)
+}
+
+/// A string, number or list of strings as text; anything else as `None`.
+fn lenient_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let text = match Value::deserialize(deserializer)? {
+        Value::String(s) => s,
+        Value::Number(n) => n.to_string(),
+        Value::Array(values) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join(" "),
+        _ => String::new(),
+    };
+    let text = text.trim();
+    Ok((!text.is_empty()).then(|| text.to_string()))
+}
+
+/// A list of strings or of objects naming a ticker or tag, e.g. `{"ticker": "AAPL:US"}`.
+fn lenient_names<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let values = match Value::deserialize(deserializer)? {
+        Value::Array(values) => values,
+        Value::String(s) => vec![Value::String(s)],
+        _ => Vec::new(),
+    };
+    Ok(values
+        .iter()
+        .filter_map(|value| match value {
+            Value::String(s) => Some(s.as_str()),
+            Value::Object(map) => ["ticker", "symbol", "name", "id"]
+                .iter()
+                .find_map(|key| map.get(*key).and_then(Value::as_str)),
+            _ => None,
+        })
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Tracks what has already been returned so that each poll only yields new stories.
+#[derive(Debug, Default)]
+struct Watermark {
+    last_published_at: Option<DateTime<Utc>>,
+    /// Where the last complete walk of the lineup ended, while later walks stopped at a
+    /// page that failed. Until a walk gets back to it, seen stories do not end the walk.
+    catch_up_to: Option<DateTime<Utc>>,
+    /// Lineup `updated_at` of every story returned, by URL.
+    seen: HashMap<String, Option<String>>,
+    /// Stories whose headline was returned but whose body has not been fetched yet.
+    announced: HashSet<String>,
+}
+
+impl Watermark {
+    fn is_empty(&self) -> bool {
+        self.last_published_at.is_none() && self.seen.is_empty()
+    }
+
+    /// Whether `story` was returned before and has been updated since.
+    fn is_update(&self, story: &BloombergArticle) -> bool {
+        match self.seen.get(&story.url) {
+            Some(updated_at) => story.updated_at.is_some() && *updated_at != story.updated_at,
+            None => false,
+        }
+    }
+
+    /// Whether `story` is at or behind the watermark, meaning older pages were already covered.
+    fn reached(&self, story: &BloombergArticle) -> bool {
+        if let Some(catch_up_to) = self.catch_up_to {
+            return story.published_at().is_some_and(|published_at| published_at <= catch_up_to);
+        }
+        if self.seen.contains_key(&story.url) {
+            return true;
+        }
+        match (self.last_published_at, story.published_at()) {
+            (Some(last), Some(published_at)) => published_at <= last,
+            _ => false,
+        }
+    }
+
+    fn record(&mut self, article: &Article) {
+        self.announced.remove(&article.url);
+        let updated_at = article.metadata.get(UPDATED_AT_KEY).and_then(Value::as_str);
+        self.seen.insert(article.url.clone(), updated_at.map(str::to_string));
+        if self.last_published_at.is_none_or(|last| article.published_at > last) {
+            self.last_published_at = Some(article.published_at);
+        }
+    }
+}
+
+/// Consecutive blocked requests and the cool-down they triggered.
+#[derive(Debug, Default)]
+struct Blocks {
+    consecutive: u32,
+    cooling_until: Option<DateTime<Utc>>,
 }
 
 pub struct Bloomberg {
-    client: reqwest::Client,
+    http: HttpClient,
+    config: BloombergConfig,
+    watermark: Mutex<Watermark>,
+    blocks: Mutex<Blocks>,
+    pending: Mutex<Vec<BloombergArticle>>,
+}
+
+impl Default for Bloomberg {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Bloomberg {
     pub fn new() -> Self {
+        Self::with_config(BloombergConfig::default())
+    }
+
+    pub fn with_config(config: BloombergConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let mut http = HttpClient::from_client(client)
+            .with_timeout(config.request_timeout)
+            .with_rate_limit(config.rate_limit)
+            .with_retry_policy(config.retry.clone());
+        if let Some((dir, ttl)) = &config.story_cache {
+            http = http.with_disk_cache(dir.clone(), *ttl);
+        }
         Self {
-            client: reqwest::Client::builder()
-                .redirect(reqwest::redirect::Policy::none())
-                .build()
-                .unwrap(),
+            config,
+            http,
+            watermark: Mutex::new(Watermark::default()),
+            blocks: Mutex::new(Blocks::default()),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// End of the current cool-down, if the feed is cooling down after repeated blocks.
+    pub fn cooling_down_until(&self) -> Option<DateTime<Utc>> {
+        self.blocks.lock().unwrap().cooling_until.filter(|until| *until > Utc::now())
+    }
+
+    /// Story and lineup cache counters for this feed.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.http.stats()
+    }
+
+    /// Publication time of the newest story returned so far.
+    pub fn last_published_at(&self) -> Option<DateTime<Utc>> {
+        self.watermark.lock().unwrap().last_published_at
+    }
+
+    /// Forgets every story seen so far, so the next poll starts from the first page again.
+    pub fn reset_watermark(&self) {
+        *self.watermark.lock().unwrap() = Watermark::default();
+        self.pending.lock().unwrap().clear();
+    }
+
+    /// Fetches every unseen story concurrently. Failed stories are reported per URL and are
+    /// not recorded in the watermark, so they are retried on the next poll.
+    pub async fn fetch_new_articles(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        let lineup = self.fetch_headlines().await?;
+        let mut report = self.fetch_pending_stories().await;
+        report.failures.splice(0..0, lineup.failures);
+        Ok(report)
+    }
+
+    /// Reads the lineup and queues unseen stories for [`Bloomberg::fetch_pending_stories`],
+    /// returning a headline-only article for each story not announced before, oldest first.
+    /// Lineup pages after the first that could not be read are reported as failures.
+    pub async fn fetch_headlines(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        self.check_cool_down()?;
+        let (stories, failures) = self.get_unseen_stories().await?;
+
+        let mut headlines = Vec::new();
+        {
+            let mut watermark = self.watermark.lock().unwrap();
+            for story in &stories {
+                if watermark.announced.insert(story.url.clone()) {
+                    headlines.push(story.headline_article());
+                }
+            }
+        }
+        let mut pending = self.pending.lock().unwrap();
+        for story in stories {
+            if !pending.iter().any(|queued| queued.url == story.url) {
+                pending.push(story);
+            }
+        }
+        headlines.sort_by_key(|article| article.published_at);
+        Ok(FetchReport { articles: headlines, failures })
+    }
+
+    /// Fetches the stories queued by [`Bloomberg::fetch_headlines`]. The articles have the
+    /// same ids as the headlines returned for them.
+    pub async fn fetch_pending_stories(&self) -> FetchReport {
+        let results: Vec<Result<Article, StoryError>> = self.enrich_pending().map(|(_, result)| result).collect().await;
+
+        let mut report = FetchReport::default();
+        for result in results {
+            match result {
+                Ok(article) => report.articles.push(article),
+                Err(failure) => report.failures.push(failure),
+            }
+        }
+        report.articles.sort_by_key(|article| article.published_at);
+        report
+    }
+
+    /// Fetches the queued stories concurrently, yielding each as soon as it is done and
+    /// recording it in the watermark. Stories that failed for a reason that may go away
+    /// are queued again for the next poll.
+    fn enrich_pending(&self) -> impl Stream<Item = (BloombergArticle, Result<Article, StoryError>)> + '_ {
+        let stories = std::mem::take(&mut *self.pending.lock().unwrap());
+        stream::iter(stories)
+            .map(move |story| async move {
+                let result = self.fetch_story(&story).await;
+                (story, result)
+            })
+            .buffer_unordered(self.config.concurrency)
+            .inspect(|(story, result)| match result {
+                Ok(article) => self.watermark.lock().unwrap().record(article),
+                Err(failure) if failure.error.is_transient() => {
+                    let mut pending = self.pending.lock().unwrap();
+                    if !pending.iter().any(|queued| queued.url == story.url) {
+                        pending.push(story.clone());
+                    }
+                }
+                Err(_) => {}
+            })
+    }
+
+    /// The headline of a story that cannot be fetched, as its final version: without a
+    /// body, or paywalled. It is recorded, so the story is not tried again.
+    fn give_up(&self, story: &BloombergArticle, error: &FeedError) -> Article {
+        let mut article = story.headline_article();
+        article.body_status = match error {
+            FeedError::Paywalled { .. } => BodyStatus::Paywalled,
+            _ => BodyStatus::Missing,
+        };
+        self.watermark.lock().unwrap().record(&article);
+        article
+    }
+
+    async fn fetch_story(&self, story: &BloombergArticle) -> Result<Article, StoryError> {
+        let result = match self.check_cool_down() {
+            Ok(()) => self.get_story(story.url.clone(), story.is_update).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(mut article) => {
+                story.annotate(&mut article);
+                Ok(article)
+            }
+            Err(e) => Err(StoryError::new(&story.url, e)),
+        }
+    }
+
+    fn check_cool_down(&self) -> Result<(), FeedError> {
+        let mut blocks = self.blocks.lock().unwrap();
+        match blocks.cooling_until {
+            Some(until) if until > Utc::now() => Err(FeedError::CoolingDown { until }),
+            Some(_) => {
+                blocks.cooling_until = None;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Counts consecutive blocks and starts a cool-down once they reach the threshold.
+    fn track_blocks<T>(&self, result: Result<T, FeedError>) -> Result<T, FeedError> {
+        let result = result.map_err(Self::classify_redirect);
+        let mut blocks = self.blocks.lock().unwrap();
+        match &result {
+            Ok(_) => blocks.consecutive = 0,
+            Err(FeedError::Blocked { .. }) => {
+                blocks.consecutive += 1;
+                if blocks.consecutive >= self.config.block_threshold {
+                    blocks.consecutive = 0;
+                    blocks.cooling_until = chrono::Duration::from_std(self.config.cool_down)
+                        .ok()
+                        .map(|cool_down| Utc::now() + cool_down);
+                }
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// Redirects are never followed, so robot checks and paywalls often show up as one.
+    fn classify_redirect(error: FeedError) -> FeedError {
+        match error {
+            FeedError::Redirected { url, location: Some(location) }
+                if BLOCK_REDIRECTS.iter().any(|marker| location.contains(marker)) =>
+            {
+                FeedError::Blocked { url, reason: format!("redirected to {}", location) }
+            }
+            FeedError::Redirected { url, location: Some(location) }
+                if PAYWALL_REDIRECTS.iter().any(|marker| location.contains(marker)) =>
+            {
+                FeedError::Paywalled { url }
+            }
+            error => error,
+        }
+    }
+
+    /// Walks the lineup page by page until it reaches stories behind the watermark and
+    /// returns the stories that have not been seen yet, newest first. A page that fails
+    /// after the first is reported and leaves the watermark where the walk was meant to
+    /// end, so the next poll walks past the stories returned now to reach the rest. Not
+    /// reaching the watermark within `max_pages` is reported too; those stories are skipped.
+    async fn get_unseen_stories(&self) -> Result<(Vec<BloombergArticle>, Vec<StoryError>), FeedError> {
+        let mut unseen = Vec::new();
+        let mut failures = Vec::new();
+        let mut complete = false;
+        for page in 1..=self.config.max_pages {
+            let stories = match self.get_stories(page).await {
+                Ok(stories) => stories,
+                Err(e) if page == 1 => return Err(e),
+                Err(e) => {
+                    failures.push(StoryError::new(&self.lineup_url(page), e));
+                    break;
+                }
+            };
+            let page_len = stories.len();
+
+            let (reached, first_poll) = {
+                let watermark = self.watermark.lock().unwrap();
+                let mut reached = false;
+                for mut story in stories {
+                    reached |= watermark.reached(&story);
+                    story.is_update = watermark.is_update(&story);
+                    if story.is_update || !watermark.seen.contains_key(&story.url) {
+                        unseen.push(story);
+                    }
+                }
+                (reached, watermark.is_empty())
+            };
+
+            // Without a watermark there is nothing to catch up to, so only the latest page is taken.
+            if reached || first_poll || page_len < self.config.page_size {
+                complete = true;
+                break;
+            }
         }
+
+        let mut watermark = self.watermark.lock().unwrap();
+        if complete {
+            watermark.catch_up_to = None;
+        } else if failures.is_empty() {
+            let url = self.lineup_url(self.config.max_pages);
+            let message = format!("previous poll not reached within {} lineup pages", self.config.max_pages);
+            failures.push(StoryError::new(&url, FeedError::parse(&url, message)));
+        } else if watermark.catch_up_to.is_none() {
+            watermark.catch_up_to = watermark.last_published_at;
+        }
+
+        let mut urls = HashSet::new();
+        unseen.retain(|story| urls.insert(story.url.clone()));
+        Ok((unseen, failures))
     }
 
-    async fn get_story(&self, url: String) -> Result<Article, Box<dyn std::error::Error>> {
-        let response = self.client.get(&url)
-            .header("Host", "www.bloomberg.com")
-            .header("Cookie", "exp_pref=AMER; country_code=US")
+    fn request(&self, url: &str) -> reqwest::RequestBuilder {
+        self.http.get(url)
+            .header("Cookie", self.config.cookie())
             .header("sec-ch-ua-mobile", "?0")
             .header("sec-ch-ua-platform", "\"macOS\"")
             .header("user-agent", "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36")
@@ -40,72 +666,138 @@ impl Bloomberg {
             .header("sec-fetch-site", "same-origin")
             .header("sec-fetch-mode", "cors") 
             .header("sec-fetch-dest", "empty")
-            .header("referer", "https://www.bloomberg.com/latest")
+            .header("referer", format!("{}/latest", self.config.base_url))
             .header("accept-language", "en-US,en;q=0.9")
             .header("priority", "u=1, i")
-            .send()
-            .await?
-            .text()
-            .await?;
+    }
 
-        let document = Html::parse_document(&response);
+    /// Updated stories bypass the story cache.
+    async fn get_story(&self, url: String, is_update: bool) -> Result<Article, FeedError> {
+        let result = if is_update {
+            self.http.fetch_revalidated(self.request(&url)).await
+        } else {
+            self.http.fetch_cached(self.request(&url)).await
+        };
+        self.track_blocks(result.and_then(|response| Self::parse_story(&url, &response.body)))
+    }
+
+    /// Builds an article from a story page, taking the body from the embedded story JSON
+    /// and falling back to the rendered paragraphs. Robot check pages and paywalls
+    /// without any text are reported as [`FeedError::Blocked`] and [`FeedError::Paywalled`].
+    pub fn parse_story(url: &str, html: &str) -> Result<Article, FeedError> {
+        if let Some(reason) = http::interstitial_reason(html) {
+            return Err(FeedError::Blocked { url: url.to_string(), reason });
+        }
+        let document = Html::parse_document(html);
 
-        let title = self.select_meta_content(&document, "og:title")?;
-        let author = self.select_meta_content(&document, "parsely-author")?;
-        let published_at = self.select_meta_content(&document, "parsely-pub-date")?;
-        let published_at = DateTime::parse_from_rfc3339(&published_at)?.with_timezone(&Utc);
-        let description = self.select_meta_content(&document, "og:description")?;
+        let metadata = extract::extract_document(&document);
+        let paywalled = Self::is_paywalled(&document);
+        let body = Self::story_json_body(&document)
+            .or(metadata.body)
+            .or_else(|| Self::story_paragraphs(&document))
+            .unwrap_or_default();
+        let description = metadata.summary.unwrap_or_default();
+        if paywalled && body.is_empty() && description.is_empty() {
+            return Err(FeedError::Paywalled { url: url.to_string() });
+        }
+
+        let title = metadata.title.ok_or_else(|| FeedError::parse(url, "story title not found"))?;
+        let author = metadata.author.unwrap_or_default();
+        let published_at = metadata
+            .published_at
+            .ok_or_else(|| FeedError::parse(url, "story publication date not found"))?;
+
+        let body_status = if body.is_empty() {
+            if paywalled { BodyStatus::Paywalled } else { BodyStatus::Missing }
+        } else if paywalled {
+            BodyStatus::Paywalled
+        } else if body.len() < description.len() * MIN_BODY_TO_TEASER_RATIO {
+            BodyStatus::Truncated
+        } else {
+            BodyStatus::Full
+        };
 
         Ok(Article {
+            id: article_id(url),
             title,
             author,
-            body: description,
+            body: if body.is_empty() { description.clone() } else { body },
+            summary: Some(description),
+            body_status,
             url: url.to_string(),
             source: "Bloomberg".to_string(),
+            origin: None,
             published_at,
+            fetched_at: Some(Utc::now()),
+            language: metadata.language,
+            tags: metadata.tags,
+            ..Default::default()
         })
     }
 
-    fn select_meta_content(&self, document: &Html, property: &str) -> Result<String, Box<dyn std::error::Error>> {
-        let selector = scraper::Selector::parse(&format!("meta[property='{}'], meta[name='{}']", property, property))
-            .map_err(|e| format!("Failed to parse selector: {}", e))?;
-        
-        let content = document
+    /// Story text from the `__NEXT_DATA__` payload, where the body is a tree of typed nodes.
+    fn story_json_body(document: &Html) -> Option<String> {
+        let selector = scraper::Selector::parse("script#__NEXT_DATA__").ok()?;
+        let json = document.select(&selector).next()?.text().collect::<String>();
+        let data: serde_json::Value = serde_json::from_str(&json).ok()?;
+
+        let story = data.pointer("/props/pageProps/story")?;
+        let body = match story.get("body")? {
+            serde_json::Value::String(html) => Html::parse_fragment(html)
+                .root_element()
+                .text()
+                .collect::<String>(),
+            node => {
+                let mut paragraphs = Vec::new();
+                collect_paragraphs(node, &mut paragraphs);
+                paragraphs.join("\n\n")
+            }
+        };
+
+        let body = body.trim().to_string();
+        (!body.is_empty()).then_some(body)
+    }
+
+    fn story_paragraphs(document: &Html) -> Option<String> {
+        let selector = scraper::Selector::parse(".body-content p, [class*='body-copy'] p, article p").ok()?;
+        let paragraphs: Vec<String> = document
             .select(&selector)
-            .next()
-            .and_then(|element| element.value().attr("content"))
-            .ok_or_else(|| format!("Meta tag with property '{}' not found", property))?
-            .to_string();
-
-        Ok(content)
-    }
-    
-    async fn get_stories(&self, page: &str) -> Result<Vec<BloombergArticle>, Box<dyn std::error::Error>> {
-        let response = self.client
-            .get("https://www.bloomberg.com/lineup-next/api/stories")
-            .query(&[
-                ("limit", "25"),
-                ("brand", "MARKETS"), 
-                ("pageNumber", &page),
-                ("types", "ARTICLE")
-            ])
-            .header("Host", "www.bloomberg.com")
-            .header("Cookie", "exp_pref=AMER; country_code=US")
-            .header("sec-ch-ua-mobile", "?0")
-            .header("sec-ch-ua-platform", "\"macOS\"")
-            .header("user-agent", "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36")
-            .header("sec-ch-ua", "\"Google Chrome\";v=\"131\", \"Chromium\";v=\"131\", \"Not_A Brand\";v=\"24\"")
-            .header("accept", "*/*")
-            .header("sec-fetch-site", "same-origin")
-            .header("sec-fetch-mode", "cors") 
-            .header("sec-fetch-dest", "empty")
-            .header("referer", "https://www.bloomberg.com/latest")
-            .header("accept-language", "en-US,en;q=0.9")
-            .header("priority", "u=1, i")
-            .send()
-            .await?;
+            .map(|p| p.text().collect::<String>().trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+
+        (!paragraphs.is_empty()).then(|| paragraphs.join("\n\n"))
+    }
+
+    fn is_paywalled(document: &Html) -> bool {
+        let selector = scraper::Selector::parse("[class*='paywall'], [id*='paywall'], [data-component*='paywall']").unwrap();
+        document.select(&selector).next().is_some()
+    }
+
+    fn lineup_url(&self, page: u32) -> String {
+        let limit = self.config.page_size.to_string();
+        let page = page.to_string();
+        let brands = self.config.brands.join(",");
+        let types = self.config.types.join(",");
+        let url = format!("{}/lineup-next/api/stories", self.config.base_url);
+        let query = [
+            ("limit", limit.as_str()),
+            ("brand", brands.as_str()),
+            ("pageNumber", page.as_str()),
+            ("types", types.as_str()),
+        ];
+        reqwest::Url::parse_with_params(&url, &query).map(String::from).unwrap_or(url)
+    }
 
-        let bloomberg_articles: Vec<BloombergArticle> = response.json().await?;
+    async fn get_stories(&self, page: u32) -> Result<Vec<BloombergArticle>, FeedError> {
+        let request = self.request(&self.lineup_url(page));
+        let response = self.track_blocks(self.http.fetch(request).await)?;
+
+        let mut bloomberg_articles: Vec<BloombergArticle> =
+            serde_json::from_str(&response.body).map_err(|e| FeedError::parse(&response.url, e))?;
+        for story in &mut bloomberg_articles {
+            story.url = self.config.absolute_url(&story.url);
+        }
         Ok(bloomberg_articles)
     }
 }
@@ -113,18 +805,371 @@ impl Bloomberg {
 #[async_trait::async_trait]
 impl Feed for Bloomberg {
     async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
-        let stories = match self.get_stories("1").await {
-            Ok(stories) => stories,
-            Err(_) => return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Failed to get stories")))
-        };
+        Ok(self.fetch_report().await?.articles)
+    }
 
-        let mut articles = Vec::new();
-        for story in stories {
-            if let Ok(article) = self.get_story(story.url).await {
-                articles.push(article);
+    /// With [`BloombergConfig::headlines_first`], the headlines of new stories come
+    /// before their full versions.
+    async fn fetch_report(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        if !self.config.headlines_first {
+            return self.fetch_new_articles().await;
+        }
+        let headlines = self.fetch_headlines().await?;
+        let mut report = self.fetch_pending_stories().await;
+        report.articles.splice(0..0, headlines.articles);
+        report.failures.splice(0..0, headlines.failures);
+        Ok(report)
+    }
+
+    /// With [`BloombergConfig::headlines_first`], headlines are streamed as soon as the
+    /// lineup is read and each full story as soon as its page is fetched. A story that
+    /// fails for good is sent again as a headline without a body; one that may still
+    /// succeed is fetched on the next round, which comes later as the round counts as
+    /// failed.
+    fn subscribe(self, options: SubscribeOptions) -> Subscription {
+        if !self.config.headlines_first {
+            return subscribe(self, options);
+        }
+        poll_loop(self, options, |bloomberg, sender| {
+            Box::pin(async move {
+                // The error is not `Send` and must not live across an await.
+                let headlines = bloomberg.fetch_headlines().await.map_err(|e| e.to_string());
+                let headlines = match headlines {
+                    Ok(headlines) => headlines,
+                    Err(e) => return Polled::Failed(e),
+                };
+                if send_all(sender, headlines.articles).await == Polled::Closed {
+                    return Polled::Closed;
+                }
+                let mut polled = match headlines.failures.first() {
+                    Some(failure) => Polled::Failed(failure.to_string()),
+                    None => Polled::Ok,
+                };
+                let mut stories = Box::pin(bloomberg.enrich_pending());
+                while let Some((story, result)) = stories.next().await {
+                    let article = match result {
+                        Ok(article) => article,
+                        Err(failure) if failure.error.is_transient() => {
+                            polled = Polled::Failed(failure.to_string());
+                            continue;
+                        }
+                        Err(failure) => bloomberg.give_up(&story, &failure.error),
+                    };
+                    if sender.send(article).await.is_err() {
+                        return Polled::Closed;
+                    }
+                }
+                polled
+            })
+        })
+    }
+}
+
+fn collect_paragraphs(node: &serde_json::Value, paragraphs: &mut Vec<String>) {
+    match node {
+        serde_json::Value::Object(map) if map.get("type").and_then(|t| t.as_str()) == Some("paragraph") => {
+            let mut text = String::new();
+            collect_text(node, &mut text);
+            let text = text.trim();
+            if !text.is_empty() {
+                paragraphs.push(text.to_string());
+            }
+        }
+        serde_json::Value::Object(map) => map.values().for_each(|v| collect_paragraphs(v, paragraphs)),
+        serde_json::Value::Array(nodes) => nodes.iter().for_each(|v| collect_paragraphs(v, paragraphs)),
+        _ => {}
+    }
+}
+
+fn collect_text(node: &serde_json::Value, text: &mut String) {
+    match node {
+        serde_json::Value::Object(map) => {
+            if let Some(value) = map.get("value").and_then(|v| v.as_str()) {
+                text.push_str(value);
+            }
+            if let Some(content) = map.get("content") {
+                collect_text(content, text);
+            }
+        }
+        serde_json::Value::Array(nodes) => nodes.iter().for_each(|v| collect_text(v, text)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use hyper::{Request, Response};
+    use crate::feeds::test_util::{respond, serve};
+
+    const STORY_URL: &str = "https://www.bloomberg.com/news/articles/2024-12-05/bitcoin-tops-100-000";
+    const STORY: &str = include_str!("../../tests/fixtures/bloomberg/story.html");
+    const CAPTCHA: &str = include_str!("../../tests/fixtures/bloomberg/captcha.html");
+    const PAYWALL: &str = include_str!("../../tests/fixtures/bloomberg/paywall.html");
+
+    /// Lineup and story pages of a fake site with stories numbered from 1, where story
+    /// `n` was published `n` minutes after midnight.
+    #[derive(Default)]
+    struct Site {
+        stories: usize,
+        failing_pages: HashSet<u32>,
+        /// Status returned instead of the story page.
+        failing_stories: HashMap<usize, u16>,
+        lineup_requests: Vec<u32>,
+        story_requests: Vec<usize>,
+    }
+
+    impl Site {
+        fn handle(&mut self, request: &Request<String>) -> Response<String> {
+            let url = reqwest::Url::parse(&format!("http://site{}", request.uri())).unwrap();
+            if url.path() == "/lineup-next/api/stories" {
+                let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).unwrap().1.into_owned();
+                let (page, limit): (u32, usize) = (param("pageNumber").parse().unwrap(), param("limit").parse().unwrap());
+                self.lineup_requests.push(page);
+                if self.failing_pages.contains(&page) {
+                    return respond(500, &[], "");
+                }
+                let lineup: Vec<Value> = (1..=self.stories)
+                    .rev()
+                    .skip((page as usize - 1) * limit)
+                    .take(limit)
+                    .map(|n| {
+                        serde_json::json!({
+                            "headline": format!("Story {}", n),
+                            "url": format!("/news/articles/story-{}", n),
+                            "publishedAt": published_at(n).to_rfc3339(),
+                        })
+                    })
+                    .collect();
+                return respond(200, &[("content-type", "application/json")], &Value::from(lineup).to_string());
+            }
+            let n: usize = url.path().trim_start_matches("/news/articles/story-").parse().unwrap();
+            self.story_requests.push(n);
+            if let Some(status) = self.failing_stories.get(&n) {
+                return respond(*status, &[], "");
             }
+            let html = format!(
+                r#"<html><head><meta property="og:title" content="Story {n}">
+                <meta property="article:published_time" content="{}"></head>
+                <body><article><p>Everything about story {n}.</p></article></body></html>"#,
+                published_at(n).to_rfc3339()
+            );
+            respond(200, &[("content-type", "text/html")], &html)
+        }
+    }
+
+    fn published_at(n: usize) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2025-01-06T00:00:00Z").unwrap().with_timezone(&Utc)
+            + chrono::Duration::minutes(n as i64)
+    }
+
+    async fn site_feed(site: &Arc<Mutex<Site>>, config: BloombergConfig) -> Bloomberg {
+        let site = site.clone();
+        let base_url = serve(move |request| site.lock().unwrap().handle(&request)).await;
+        let config = config
+            .base_url(&base_url)
+            .page_size(2)
+            .rate_limit(Duration::ZERO)
+            .retry_policy(RetryPolicy::none());
+        Bloomberg::with_config(config)
+    }
+
+    /// Story numbers of the articles, newest first.
+    fn numbers(articles: &[Article]) -> Vec<usize> {
+        let mut numbers: Vec<usize> =
+            articles.iter().map(|a| a.title.trim_start_matches("Story ").parse().unwrap()).collect();
+        numbers.sort_unstable_by(|a, b| b.cmp(a));
+        numbers
+    }
+
+    #[tokio::test]
+    async fn walks_the_lineup_back_to_the_previous_poll() {
+        let site = Arc::new(Mutex::new(Site { stories: 3, ..Default::default() }));
+        let bloomberg = site_feed(&site, BloombergConfig::default()).await;
+
+        // The first poll only takes the latest page.
+        let report = bloomberg.fetch_new_articles().await.unwrap();
+        assert_eq!(numbers(&report.articles), [3, 2]);
+        assert_eq!(bloomberg.last_published_at(), Some(published_at(3)));
+
+        site.lock().unwrap().stories = 8;
+        site.lock().unwrap().lineup_requests.clear();
+        let report = bloomberg.fetch_new_articles().await.unwrap();
+        assert_eq!(numbers(&report.articles), [8, 7, 6, 5, 4]);
+        assert!(report.failures.is_empty());
+        assert_eq!(site.lock().unwrap().lineup_requests, [1, 2, 3]);
+        assert_eq!(bloomberg.last_published_at(), Some(published_at(8)));
+
+        site.lock().unwrap().lineup_requests.clear();
+        assert!(bloomberg.fetch_new_articles().await.unwrap().articles.is_empty());
+        assert_eq!(site.lock().unwrap().lineup_requests, [1]);
+    }
+
+    #[tokio::test]
+    async fn failed_lineup_page_is_caught_up_on_the_next_poll() {
+        let site = Arc::new(Mutex::new(Site { stories: 2, ..Default::default() }));
+        let bloomberg = site_feed(&site, BloombergConfig::default()).await;
+        assert_eq!(numbers(&bloomberg.fetch_new_articles().await.unwrap().articles), [2, 1]);
+
+        site.lock().unwrap().stories = 6;
+        site.lock().unwrap().failing_pages.insert(2);
+        let report = bloomberg.fetch_new_articles().await.unwrap();
+        assert_eq!(numbers(&report.articles), [6, 5]);
+        assert_eq!(report.failures.len(), 1);
+        assert!(report.failures[0].url.contains("pageNumber=2"));
+
+        // Still failing: nothing is lost and nothing is returned twice.
+        assert!(bloomberg.fetch_new_articles().await.unwrap().articles.is_empty());
+
+        site.lock().unwrap().failing_pages.clear();
+        site.lock().unwrap().stories = 7;
+        let report = bloomberg.fetch_new_articles().await.unwrap();
+        assert_eq!(numbers(&report.articles), [7, 4, 3]);
+        assert!(report.failures.is_empty());
+
+        site.lock().unwrap().lineup_requests.clear();
+        assert!(bloomberg.fetch_new_articles().await.unwrap().articles.is_empty());
+        assert_eq!(site.lock().unwrap().lineup_requests, [1]);
+    }
+
+    #[tokio::test]
+    async fn reports_stopping_at_max_pages() {
+        let site = Arc::new(Mutex::new(Site { stories: 2, ..Default::default() }));
+        let bloomberg = site_feed(&site, BloombergConfig::default().max_pages(2)).await;
+        bloomberg.fetch_new_articles().await.unwrap();
+
+        site.lock().unwrap().stories = 9;
+        site.lock().unwrap().lineup_requests.clear();
+        let report = bloomberg.fetch_new_articles().await.unwrap();
+        assert_eq!(numbers(&report.articles), [9, 8, 7, 6]);
+        assert_eq!(site.lock().unwrap().lineup_requests, [1, 2]);
+        assert_eq!(report.failures.len(), 1);
+        assert!(report.failures[0].to_string().contains("2 lineup pages"));
+    }
+
+    /// Story number and body status of the next article.
+    async fn receive(subscription: &mut Subscription) -> (usize, BodyStatus) {
+        let article = tokio::time::timeout(Duration::from_secs(5), subscription.next()).await.unwrap().unwrap();
+        (numbers(std::slice::from_ref(&article))[0], article.body_status)
+    }
+
+    #[tokio::test]
+    async fn headlines_first_settles_every_headline() {
+        let site = Arc::new(Mutex::new(Site { stories: 2, ..Default::default() }));
+        site.lock().unwrap().failing_stories.extend([(1, 404), (2, 503)]);
+        let bloomberg = site_feed(&site, BloombergConfig::default().headlines_first(true)).await;
+        let options = SubscribeOptions::default()
+            .poll_interval(Duration::from_millis(20))
+            .jitter(Duration::ZERO)
+            .max_backoff(Duration::from_millis(40));
+        let mut subscription = bloomberg.subscribe(options);
+
+        let mut received = Vec::new();
+        for _ in 0..3 {
+            received.push(receive(&mut subscription).await);
+        }
+        received.sort_by_key(|(n, status)| (*n, format!("{:?}", status)));
+        assert_eq!(received, [(1, BodyStatus::Missing), (1, BodyStatus::Pending), (2, BodyStatus::Pending)]);
+
+        // The unavailable story is tried again on later rounds, the missing one is not.
+        while site.lock().unwrap().story_requests.iter().filter(|n| **n == 2).count() < 3 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        site.lock().unwrap().failing_stories.clear();
+        assert_eq!(receive(&mut subscription).await, (2, BodyStatus::Full));
+        assert_eq!(site.lock().unwrap().story_requests.iter().filter(|n| **n == 1).count(), 1);
+    }
+
+    fn blocked() -> Result<(), FeedError> {
+        Err(FeedError::Blocked { url: STORY_URL.to_string(), reason: "robot check".to_string() })
+    }
+
+    fn redirected(location: &str) -> FeedError {
+        FeedError::Redirected { url: STORY_URL.to_string(), location: Some(location.to_string()) }
+    }
+
+    #[test]
+    fn parses_story_from_the_embedded_json() {
+        let article = Bloomberg::parse_story(STORY_URL, STORY).unwrap();
+        assert_eq!(article.title, "Bitcoin Tops $100,000 for First Time as ETF Demand Surges");
+        assert_eq!(article.author, "Olga Kharif");
+        assert_eq!(article.id, article_id(STORY_URL));
+        assert_eq!(article.published_at.to_rfc3339(), "2024-12-05T02:42:11+00:00");
+        assert_eq!(article.body_status, BodyStatus::Full);
+        assert!(article.body.starts_with("Bitcoin rose above $100,000 for the first time"));
+        assert!(article.body.ends_with("as much as 4.6% to $103,619 in Asia on Thursday."));
+        assert!(!article.body.contains("Rendered paragraph"));
+        assert_eq!(article.summary.as_deref(), Some("Bitcoin rose above $100,000 for the first time."));
+    }
+
+    #[test]
+    fn robot_check_page_is_blocked() {
+        match Bloomberg::parse_story(STORY_URL, CAPTCHA) {
+            Err(FeedError::Blocked { url, .. }) => assert_eq!(url, STORY_URL),
+            other => panic!("expected a block, got {:?}", other),
         }
-        
-        Ok(articles)
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn paywalled_story_keeps_the_description() {
+        let article = Bloomberg::parse_story(STORY_URL, PAYWALL).unwrap();
+        assert_eq!(article.body_status, BodyStatus::Paywalled);
+        assert!(article.body.starts_with("Goldman Sachs economists now expect"));
+
+        let without_description = PAYWALL.replace("og:description", "og:unused");
+        assert!(matches!(
+            Bloomberg::parse_story(STORY_URL, &without_description),
+            Err(FeedError::Paywalled { .. })
+        ));
+    }
+
+    #[test]
+    fn classifies_redirects() {
+        assert!(matches!(
+            Bloomberg::classify_redirect(redirected("https://www.bloomberg.com/tosv2.html?vid=&uuid=1")),
+            FeedError::Blocked { .. }
+        ));
+        assert!(matches!(
+            Bloomberg::classify_redirect(redirected("https://www.bloomberg.com/subscriptions?in_source=paywall")),
+            FeedError::Paywalled { .. }
+        ));
+        assert!(matches!(
+            Bloomberg::classify_redirect(redirected("https://www.bloomberg.com/news/articles/2024-12-05/new-url")),
+            FeedError::Redirected { location: Some(_), .. }
+        ));
+        let no_location = FeedError::Redirected { url: STORY_URL.to_string(), location: None };
+        assert!(matches!(Bloomberg::classify_redirect(no_location), FeedError::Redirected { location: None, .. }));
+    }
+
+    #[test]
+    fn cools_down_after_consecutive_blocks() {
+        let config = BloombergConfig::default().block_threshold(3).cool_down(Duration::from_secs(60));
+        let bloomberg = Bloomberg::with_config(config);
+
+        // A success in between resets the count.
+        for result in [blocked(), blocked(), Ok(()), blocked(), blocked()] {
+            let _ = bloomberg.track_blocks(result);
+        }
+        assert!(bloomberg.cooling_down_until().is_none());
+        assert!(bloomberg.check_cool_down().is_ok());
+
+        // Redirects to the robot check count as blocks.
+        let _ = bloomberg.track_blocks::<()>(Err(redirected("/tosv2.html")));
+        let until = bloomberg.cooling_down_until().expect("cooling down after three blocks");
+        assert!(until > Utc::now() + chrono::Duration::seconds(55));
+        assert!(matches!(bloomberg.check_cool_down(), Err(FeedError::CoolingDown { .. })));
+    }
+
+    #[test]
+    fn cool_down_expires() {
+        let bloomberg = Bloomberg::with_config(BloombergConfig::default().block_threshold(1));
+        let _ = bloomberg.track_blocks(blocked());
+        assert!(bloomberg.check_cool_down().is_err());
+
+        bloomberg.blocks.lock().unwrap().cooling_until = Some(Utc::now() - chrono::Duration::seconds(1));
+        assert!(bloomberg.cooling_down_until().is_none());
+        assert!(bloomberg.check_cool_down().is_ok());
+        assert!(bloomberg.blocks.lock().unwrap().cooling_until.is_none());
+    }
+}
@@ -0,0 +1,722 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use super::base::{article_id, Article, BodyStatus, Feed};
+use super::extract::parse_date;
+
+const SOURCE: &str = "Economic Calendar";
+/// Key in [`Article::metadata`] holding the [`CalendarNotice`].
+pub const CALENDAR_KEY: &str = "calendar";
+
+/// How much a release is expected to move markets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Importance {
+    Low,
+    #[default]
+    Medium,
+    /// CPI, FOMC decisions, non-farm payrolls and the like.
+    High,
+}
+
+impl Importance {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Importance::Low => "low",
+            Importance::Medium => "medium",
+            Importance::High => "high",
+        }
+    }
+
+    /// iCalendar `PRIORITY`: 1-4 is high, 5 medium and 6-9 low. 0 means undefined.
+    fn from_priority(priority: u32) -> Option<Self> {
+        match priority {
+            1..=4 => Some(Importance::High),
+            5 => Some(Importance::Medium),
+            6..=9 => Some(Importance::Low),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Importance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Importance {
+    type Err = String;
+
+    /// Accepts the names, `1` to `3`, and the "impact" wording calendar exports use.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "low" | "1" | "l" | "low impact" | "holiday" => Ok(Importance::Low),
+            "medium" | "moderate" | "2" | "m" | "medium impact" => Ok(Importance::Medium),
+            "high" | "3" | "h" | "high impact" | "critical" => Ok(Importance::High),
+            other => Err(format!("Unknown importance: {}", other)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Importance {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::Number(n) => n.to_string().parse().map_err(serde::de::Error::custom),
+            Value::String(s) => s.parse().map_err(serde::de::Error::custom),
+            other => Err(serde::de::Error::custom(format!("Unknown importance: {}", other))),
+        }
+    }
+}
+
+/// A scheduled release such as a CPI print or an FOMC decision.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    /// Derived from the title and time when the calendar has none.
+    #[serde(default, alias = "uid")]
+    pub id: String,
+    #[serde(alias = "event", alias = "name")]
+    pub title: String,
+    #[serde(alias = "date", alias = "datetime", alias = "time", alias = "start", deserialize_with = "event_time")]
+    pub starts_at: DateTime<Utc>,
+    #[serde(default, alias = "impact")]
+    pub importance: Importance,
+    /// Country or currency the release is for, e.g. `US` or `USD`.
+    #[serde(default, alias = "currency")]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub forecast: Option<String>,
+    #[serde(default)]
+    pub previous: Option<String>,
+    /// The released figure, once the calendar has it.
+    #[serde(default)]
+    pub actual: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl CalendarEvent {
+    pub fn new(title: &str, starts_at: DateTime<Utc>, importance: Importance) -> Self {
+        let mut event = Self {
+            id: String::new(),
+            title: title.to_string(),
+            starts_at,
+            importance,
+            country: None,
+            forecast: None,
+            previous: None,
+            actual: None,
+            description: None,
+        };
+        event.fill_id();
+        event
+    }
+
+    fn fill_id(&mut self) {
+        if self.id.is_empty() {
+            self.id = format!("{}@{}", self.title.trim().to_lowercase(), self.starts_at.timestamp());
+        }
+    }
+}
+
+fn event_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(s) => parse_event_time(&s).ok_or_else(|| serde::de::Error::custom(format!("Invalid date: {}", s))),
+        Value::Number(n) => n
+            .as_i64()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0))
+            .ok_or_else(|| serde::de::Error::custom(format!("Invalid timestamp: {}", n))),
+        other => Err(serde::de::Error::custom(format!("Invalid date: {}", other))),
+    }
+}
+
+/// Dates as calendar exports write them. Times without an offset are taken as UTC.
+fn parse_event_time(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    parse_date(value).or_else(|| {
+        ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%m/%d/%Y %H:%M", "%m/%d/%Y %I:%M%p"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .or_else(|| NaiveDate::parse_from_str(value, "%m/%d/%Y").ok()?.and_hms_opt(0, 0, 0))
+            .map(|date| date.and_utc())
+    })
+}
+
+/// Whether a notice announces a release or reports that it happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeKind {
+    Upcoming,
+    Released,
+}
+
+/// What an [`EconomicCalendar`] article is about, stored in its metadata.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalendarNotice {
+    pub kind: NoticeKind,
+    pub event: CalendarEvent,
+}
+
+/// The notice an article from an [`EconomicCalendar`] carries.
+pub fn notice(article: &Article) -> Option<CalendarNotice> {
+    serde_json::from_value(article.metadata.get(CALENDAR_KEY)?.clone()).ok()
+}
+
+/// Feed of scheduled economic releases loaded from an iCalendar, CSV or JSON file.
+/// Each event is announced `lead_time` before it starts ([`NoticeKind::Upcoming`]) and
+/// reported once its time has come ([`NoticeKind::Released`]), as articles carrying a
+/// [`CalendarNotice`].
+pub struct EconomicCalendar {
+    events: Vec<CalendarEvent>,
+    lead_time: Duration,
+    release_window: Duration,
+    min_importance: Importance,
+    notified: Mutex<HashSet<(String, NoticeKind)>>,
+}
+
+impl EconomicCalendar {
+    pub fn from_events(mut events: Vec<CalendarEvent>) -> Self {
+        for event in &mut events {
+            event.fill_id();
+        }
+        events.sort_by_key(|event| event.starts_at);
+        Self {
+            events,
+            lead_time: Duration::hours(1),
+            release_window: Duration::hours(1),
+            min_importance: Importance::Low,
+            notified: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Reads `.ics`, `.csv` or `.json` files, going by the extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+        let events = match extension.as_str() {
+            "ics" | "ical" => parse_ics(&text),
+            "csv" => parse_csv(&text),
+            "json" => parse_json(&text),
+            other => return Err(format!("{}: unsupported calendar format {:?}", path.display(), other).into()),
+        }
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self::from_events(events))
+    }
+
+    /// How long before an event the upcoming notice is emitted.
+    pub fn with_lead_time(mut self, lead_time: Duration) -> Self {
+        self.lead_time = lead_time;
+        self
+    }
+
+    /// How long after an event it is still reported as released. Older events, e.g.
+    /// the past part of a calendar loaded at startup, are skipped.
+    pub fn with_release_window(mut self, release_window: Duration) -> Self {
+        self.release_window = release_window;
+        self
+    }
+
+    /// Ignores events below this importance.
+    pub fn with_min_importance(mut self, min_importance: Importance) -> Self {
+        self.min_importance = min_importance;
+        self
+    }
+
+    pub fn events(&self) -> &[CalendarEvent] {
+        &self.events
+    }
+
+    /// Events of at least `min_importance` starting between now and `within` from now,
+    /// soonest first, e.g. to tighten risk ahead of a release.
+    pub fn upcoming(&self, within: Duration, min_importance: Importance) -> Vec<&CalendarEvent> {
+        let now = Utc::now();
+        self.events
+            .iter()
+            .filter(|event| event.importance >= min_importance)
+            .filter(|event| event.starts_at > now && event.starts_at <= now + within)
+            .collect()
+    }
+
+    /// Notices due at `now` that have not been emitted yet.
+    pub fn notices_at(&self, now: DateTime<Utc>) -> Vec<Article> {
+        let mut notified = self.notified.lock().unwrap();
+        let mut articles = Vec::new();
+        for event in self.events.iter().filter(|event| event.importance >= self.min_importance) {
+            let kind = if now >= event.starts_at {
+                if now - event.starts_at > self.release_window {
+                    continue;
+                }
+                NoticeKind::Released
+            } else if now >= event.starts_at - self.lead_time {
+                NoticeKind::Upcoming
+            } else {
+                continue;
+            };
+            if notified.insert((event.id.clone(), kind)) {
+                articles.push(notice_article(event, kind, now));
+            }
+        }
+        articles
+    }
+}
+
+#[async_trait]
+impl Feed for EconomicCalendar {
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        Ok(self.notices_at(Utc::now()))
+    }
+}
+
+fn notice_article(event: &CalendarEvent, kind: NoticeKind, now: DateTime<Utc>) -> Article {
+    let place = event.country.as_deref().map(|c| format!("{} ", c)).unwrap_or_default();
+    let (title, kind_tag, published_at) = match kind {
+        NoticeKind::Upcoming => (
+            format!(
+                "Upcoming {} importance release: {}{} at {}",
+                event.importance,
+                place,
+                event.title,
+                event.starts_at.format("%Y-%m-%d %H:%M UTC")
+            ),
+            "upcoming",
+            now,
+        ),
+        NoticeKind::Released => (
+            format!("Released: {}{}", place, event.title),
+            "released",
+            event.starts_at,
+        ),
+    };
+
+    // An actual figure before the release can only be a stale calendar entry.
+    let actual = if kind == NoticeKind::Released { &event.actual } else { &None };
+    let mut lines = Vec::new();
+    for (label, value) in [("Actual", actual), ("Forecast", &event.forecast), ("Previous", &event.previous)] {
+        if let Some(value) = value {
+            lines.push(format!("{}: {}", label, value));
+        }
+    }
+    if let Some(description) = &event.description {
+        lines.push(description.clone());
+    }
+
+    let url = format!("calendar:{}/{}", event.id, kind_tag);
+    let mut metadata = BTreeMap::new();
+    if let Ok(value) = serde_json::to_value(CalendarNotice { kind, event: event.clone() }) {
+        metadata.insert(CALENDAR_KEY.to_string(), value);
+    }
+    Article {
+        id: article_id(&url),
+        title: title.clone(),
+        author: String::new(),
+        body: if lines.is_empty() { title } else { lines.join("\n") },
+        summary: None,
+        body_status: BodyStatus::Full,
+        url,
+        source: SOURCE.to_string(),
+        origin: None,
+        published_at,
+        fetched_at: Some(now),
+        language: None,
+        tags: vec![
+            "economic-calendar".to_string(),
+            kind_tag.to_string(),
+            format!("{}-importance", event.importance),
+        ],
+        tickers: Vec::new(),
+        metadata,
+    }
+}
+
+/// A JSON array of events, or an object with an `events` array.
+fn parse_json(text: &str) -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
+    let value: Value = serde_json::from_str(text)?;
+    let events = match value {
+        Value::Object(mut map) => map.remove("events").unwrap_or(Value::Array(Vec::new())),
+        value => value,
+    };
+    Ok(serde_json::from_value(events)?)
+}
+
+/// A CSV file with a header row. Recognised columns, in any order and case: `id`,
+/// `title`/`event`, `datetime`/`date` plus an optional `time`, `importance`/`impact`,
+/// `country`/`currency`, `forecast`, `previous`, `actual` and `description`.
+fn parse_csv(text: &str) -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
+    // Rows are numbered by line, as an editor shows them.
+    let mut rows = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| (index + 1, csv_fields(line)));
+    let header: Vec<String> = rows
+        .next()
+        .ok_or("empty calendar")?
+        .1
+        .iter()
+        .map(|name| name.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|name| names.contains(&name.as_str()));
+    let title_column = column(&["title", "event", "name"]).ok_or("no title column")?;
+    let date_column = column(&["datetime", "starts_at", "start", "date"]).ok_or("no date column")?;
+    let time_column = column(&["time"]).filter(|column| *column != date_column);
+    let id_column = column(&["id", "uid"]);
+    let importance_column = column(&["importance", "impact"]);
+    let country_column = column(&["country", "currency"]);
+    let forecast_column = column(&["forecast", "consensus"]);
+    let previous_column = column(&["previous", "prior"]);
+    let actual_column = column(&["actual"]);
+    let description_column = column(&["description", "notes"]);
+
+    let mut events = Vec::new();
+    for (number, row) in rows {
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| row.get(column))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let date = match field(time_column) {
+            Some(time) if !time.eq_ignore_ascii_case("all day") => {
+                format!("{} {}", field(Some(date_column)).unwrap_or_default(), time)
+            }
+            _ => field(Some(date_column)).unwrap_or_default(),
+        };
+        let starts_at = parse_event_time(&date)
+            .ok_or_else(|| format!("row {}: invalid date {:?}", number, date))?;
+        let importance = match field(importance_column) {
+            Some(importance) => importance.parse().map_err(|e| format!("row {}: {}", number, e))?,
+            None => Importance::default(),
+        };
+
+        let mut event = CalendarEvent::new(&field(Some(title_column)).unwrap_or_default(), starts_at, importance);
+        if let Some(id) = field(id_column) {
+            event.id = id;
+        }
+        event.country = field(country_column);
+        event.forecast = field(forecast_column);
+        event.previous = field(previous_column);
+        event.actual = field(actual_column);
+        event.description = field(description_column);
+        events.push(event);
+    }
+    Ok(events)
+}
+
+/// Splits a CSV line, honouring double quotes and `""` escapes. Quoted fields
+/// spanning several lines are not supported.
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// The `VEVENT`s of an iCalendar file. `DTSTART` values with a `TZID` are converted
+/// from that IANA time zone; an event in any other zone is an error. Times without a
+/// zone are taken as UTC. Importance comes from `X-IMPORTANCE`, else `PRIORITY`.
+fn parse_ics(text: &str) -> Result<Vec<CalendarEvent>, Box<dyn std::error::Error>> {
+    // Long lines are folded onto continuation lines starting with a space or tab.
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut properties: Option<Vec<(String, String, String)>> = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        let name = name.to_uppercase();
+        match (name.as_str(), value) {
+            ("BEGIN", "VEVENT") => properties = Some(Vec::new()),
+            ("END", "VEVENT") => {
+                if let Some(properties) = properties.take() {
+                    events.push(ics_event(&properties)?);
+                }
+            }
+            _ => {
+                if let Some(properties) = properties.as_mut() {
+                    properties.push((name, params.to_string(), unescape_ics(value)));
+                }
+            }
+        }
+    }
+    Ok(events)
+}
+
+fn ics_event(properties: &[(String, String, String)]) -> Result<CalendarEvent, Box<dyn std::error::Error>> {
+    let property = |name: &str| {
+        properties
+            .iter()
+            .find(|(n, _, _)| n == name)
+            .map(|(_, params, value)| (params.as_str(), value.trim().to_string()))
+    };
+
+    let title = property("SUMMARY").map(|(_, v)| v).ok_or("event without SUMMARY")?;
+    let (params, start) = property("DTSTART").ok_or_else(|| format!("{}: no DTSTART", title))?;
+    let param = |key: &str| {
+        params.split(';').find_map(|param| {
+            let (k, v) = param.split_once('=')?;
+            k.eq_ignore_ascii_case(key).then(|| v.trim_matches('"'))
+        })
+    };
+    let starts_at = if param("VALUE").is_some_and(|value| value.eq_ignore_ascii_case("DATE")) {
+        NaiveDate::parse_from_str(&start, "%Y%m%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|date| date.and_utc())
+    } else {
+        let local = NaiveDateTime::parse_from_str(start.trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok();
+        match (local, param("TZID")) {
+            (Some(local), Some(tzid)) if !start.ends_with('Z') => {
+                let zone: Tz = tzid.parse().map_err(|_| format!("{}: unknown TZID {}", title, tzid))?;
+                // Times skipped by a DST change don't exist; ambiguous ones take the first.
+                let starts_at = zone
+                    .from_local_datetime(&local)
+                    .earliest()
+                    .ok_or_else(|| format!("{}: {} does not exist in {}", title, start, tzid))?;
+                Some(starts_at.with_timezone(&Utc))
+            }
+            (local, _) => local.map(|local| local.and_utc()),
+        }
+    }
+    .ok_or_else(|| format!("{}: invalid DTSTART {}", title, start))?;
+
+    let importance = property("X-IMPORTANCE")
+        .and_then(|(_, v)| v.parse().ok())
+        .or_else(|| property("PRIORITY").and_then(|(_, v)| v.parse().ok()).and_then(Importance::from_priority))
+        .unwrap_or_default();
+
+    let mut event = CalendarEvent::new(&title, starts_at, importance);
+    if let Some((_, uid)) = property("UID") {
+        event.id = uid;
+    }
+    event.country = property("X-COUNTRY").or_else(|| property("LOCATION")).map(|(_, v)| v);
+    event.forecast = property("X-FORECAST").map(|(_, v)| v);
+    event.previous = property("X-PREVIOUS").map(|(_, v)| v);
+    event.actual = property("X-ACTUAL").map(|(_, v)| v);
+    event.description = property("DESCRIPTION").map(|(_, v)| v).filter(|v| !v.is_empty());
+    Ok(event)
+}
+
+/// Undoes TEXT escaping in one pass, so `\\n` stays a backslash followed by `n`.
+fn unescape_ics(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c @ ('\\' | ',' | ';')) => unescaped.push(c),
+            Some(c) => {
+                unescaped.push('\\');
+                unescaped.push(c);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ics_start(dtstart: &str) -> Result<DateTime<Utc>, Box<dyn std::error::Error>> {
+        let ics = format!("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:CPI\r\n{}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n", dtstart);
+        Ok(parse_ics(&ics)?[0].starts_at)
+    }
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn ics_converts_tzid() {
+        assert_eq!(ics_start("DTSTART;TZID=America/New_York:20250115T083000").unwrap(), utc("2025-01-15T13:30:00Z"));
+        assert_eq!(ics_start("DTSTART;TZID=\"America/New_York\":20250715T083000").unwrap(), utc("2025-07-15T12:30:00Z"));
+        assert_eq!(ics_start("DTSTART;TZID=Europe/London:20250715T070000").unwrap(), utc("2025-07-15T06:00:00Z"));
+        assert_eq!(ics_start("DTSTART;TZID=Europe/London:20250715T070000Z").unwrap(), utc("2025-07-15T07:00:00Z"));
+        assert_eq!(ics_start("DTSTART:20250715T070000").unwrap(), utc("2025-07-15T07:00:00Z"));
+        assert_eq!(ics_start("DTSTART;VALUE=DATE:20250715").unwrap(), utc("2025-07-15T00:00:00Z"));
+    }
+
+    #[test]
+    fn ics_rejects_unknown_or_skipped_times() {
+        let error = ics_start("DTSTART;TZID=Eastern Standard Time:20250115T083000").unwrap_err();
+        assert!(error.to_string().contains("unknown TZID"));
+        // Clocks went from 02:00 to 03:00 that night.
+        assert!(ics_start("DTSTART;TZID=America/New_York:20250309T023000").is_err());
+    }
+
+    #[test]
+    fn parses_ics_events() {
+        let ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n\
+BEGIN:VEVENT\r\nUID:cpi-2025-01\r\nSUMMARY:US CPI\r\nDTSTART:20250115T133000Z\r\nPRIORITY:1\r\n\
+LOCATION:US\r\nX-FORECAST:2.9%\r\nX-PREVIOUS:2.7%\r\n\
+DESCRIPTION:Consumer prices\\, all items.\\nSeasonally adjusted\r\n  and monthly.\r\nEND:VEVENT\r\n\
+BEGIN:VEVENT\r\nSUMMARY:Bank holiday\r\nDTSTART;VALUE=DATE:20250120\r\nX-IMPORTANCE:low\r\nEND:VEVENT\r\n\
+END:VCALENDAR\r\n";
+        let events = parse_ics(ics).unwrap();
+        assert_eq!(events.len(), 2);
+
+        let cpi = &events[0];
+        assert_eq!(cpi.id, "cpi-2025-01");
+        assert_eq!(cpi.title, "US CPI");
+        assert_eq!(cpi.starts_at, utc("2025-01-15T13:30:00Z"));
+        assert_eq!(cpi.importance, Importance::High);
+        assert_eq!(cpi.country.as_deref(), Some("US"));
+        assert_eq!(cpi.forecast.as_deref(), Some("2.9%"));
+        assert_eq!(cpi.previous.as_deref(), Some("2.7%"));
+        assert_eq!(cpi.description.as_deref(), Some("Consumer prices, all items.\nSeasonally adjusted and monthly."));
+
+        let holiday = &events[1];
+        assert_eq!(holiday.id, format!("bank holiday@{}", utc("2025-01-20T00:00:00Z").timestamp()));
+        assert_eq!(holiday.importance, Importance::Low);
+
+        assert!(parse_ics("BEGIN:VEVENT\nDTSTART:20250115T133000Z\nEND:VEVENT\n").is_err());
+    }
+
+    #[test]
+    fn parses_csv_events() {
+        let csv = "Date,Time,Currency,Impact,Event,Forecast,Previous\n\
+2025-01-15,13:30,USD,High Impact,\"CPI m/m, \"\"core\"\"\",0.2%,0.3%\n\
+\n\
+01/20/2025,All Day,USD,Holiday,Bank Holiday,,\n";
+        let events = parse_csv(csv).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].title, r#"CPI m/m, "core""#);
+        assert_eq!(events[0].starts_at, utc("2025-01-15T13:30:00Z"));
+        assert_eq!(events[0].importance, Importance::High);
+        assert_eq!(events[0].country.as_deref(), Some("USD"));
+        assert_eq!(events[0].forecast.as_deref(), Some("0.2%"));
+        assert_eq!(events[0].actual, None);
+        assert_eq!(events[1].starts_at, utc("2025-01-20T00:00:00Z"));
+        assert_eq!(events[1].importance, Importance::Low);
+    }
+
+    #[test]
+    fn csv_errors_name_the_row() {
+        let error = parse_csv("title,date\nCPI,2025-01-15 13:30\n\nGDP,soon\n").unwrap_err();
+        assert_eq!(error.to_string(), r#"row 4: invalid date "soon""#);
+        let error = parse_csv("title,date,importance\nCPI,2025-01-15 13:30,urgent\n").unwrap_err();
+        assert_eq!(error.to_string(), "row 2: Unknown importance: urgent");
+        assert!(parse_csv("event,when\nCPI,2025-01-15\n").is_err());
+    }
+
+    /// Titles and kinds of the notices due at `now`.
+    fn notices(calendar: &EconomicCalendar, now: &str) -> Vec<(String, NoticeKind)> {
+        calendar
+            .notices_at(utc(now))
+            .iter()
+            .map(|article| {
+                let notice = notice(article).unwrap();
+                (notice.event.title, notice.kind)
+            })
+            .collect()
+    }
+
+    fn cpi_calendar() -> EconomicCalendar {
+        let mut cpi = CalendarEvent::new("CPI", utc("2025-01-15T13:30:00Z"), Importance::High);
+        cpi.actual = Some("2.9%".to_string());
+        EconomicCalendar::from_events(vec![cpi])
+            .with_lead_time(Duration::minutes(30))
+            .with_release_window(Duration::minutes(15))
+    }
+
+    #[test]
+    fn announces_upcoming_events_at_the_lead_time() {
+        let calendar = cpi_calendar();
+        assert!(notices(&calendar, "2025-01-15T12:59:59Z").is_empty());
+
+        let articles = calendar.notices_at(utc("2025-01-15T13:00:00Z"));
+        assert_eq!(articles.len(), 1);
+        assert_eq!(notice(&articles[0]).unwrap().kind, NoticeKind::Upcoming);
+        assert_eq!(articles[0].published_at, utc("2025-01-15T13:00:00Z"));
+        // The actual figure is not reported before the release.
+        assert!(!articles[0].body.contains("Actual"));
+
+        assert!(notices(&calendar, "2025-01-15T13:29:59Z").is_empty());
+    }
+
+    #[test]
+    fn reports_releases_within_the_release_window() {
+        let calendar = cpi_calendar();
+        let articles = calendar.notices_at(utc("2025-01-15T13:40:00Z"));
+        assert_eq!(articles.len(), 1);
+        assert_eq!(notice(&articles[0]).unwrap().kind, NoticeKind::Released);
+        assert_eq!(articles[0].published_at, utc("2025-01-15T13:30:00Z"));
+        assert!(articles[0].body.contains("Actual: 2.9%"));
+
+        let calendar = cpi_calendar();
+        assert_eq!(notices(&calendar, "2025-01-15T13:45:00Z"), vec![("CPI".to_string(), NoticeKind::Released)]);
+    }
+
+    #[test]
+    fn skips_events_past_the_release_window() {
+        let calendar = cpi_calendar();
+        assert!(notices(&calendar, "2025-01-15T13:45:01Z").is_empty());
+        assert!(notices(&calendar, "2025-01-16T00:00:00Z").is_empty());
+    }
+
+    #[test]
+    fn ignores_events_below_min_importance() {
+        let start = utc("2025-01-15T13:30:00Z");
+        let events = vec![
+            CalendarEvent::new("CPI", start, Importance::High),
+            CalendarEvent::new("Retail sales", start, Importance::Medium),
+            CalendarEvent::new("Bank holiday", start, Importance::Low),
+        ];
+        let calendar = EconomicCalendar::from_events(events).with_min_importance(Importance::Medium);
+        let mut titles: Vec<_> = notices(&calendar, "2025-01-15T13:00:00Z").into_iter().map(|(title, _)| title).collect();
+        titles.sort();
+        assert_eq!(titles, ["CPI", "Retail sales"]);
+    }
+
+    #[test]
+    fn emits_each_notice_once() {
+        let calendar = cpi_calendar();
+        assert_eq!(notices(&calendar, "2025-01-15T13:00:00Z"), vec![("CPI".to_string(), NoticeKind::Upcoming)]);
+        assert!(notices(&calendar, "2025-01-15T13:10:00Z").is_empty());
+        assert_eq!(notices(&calendar, "2025-01-15T13:30:00Z"), vec![("CPI".to_string(), NoticeKind::Released)]);
+        assert!(notices(&calendar, "2025-01-15T13:35:00Z").is_empty());
+    }
+
+    #[test]
+    fn ics_unescapes_in_one_pass() {
+        assert_eq!(unescape_ics(r"Rates\, yields\; spreads\nand more"), "Rates, yields; spreads\nand more");
+        assert_eq!(unescape_ics(r"C:\\new"), r"C:\new");
+        assert_eq!(unescape_ics(r"a\\,b"), r"a\,b");
+        assert_eq!(unescape_ics(r"trailing\"), r"trailing\");
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use chrono::{DateTime, Duration, Utc};
+use super::base::{article_id, Article};
+
+/// Words too common to say anything about which event a headline is about.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "has", "have", "in", "is", "it",
+    "its", "of", "on", "or", "says", "that", "the", "to", "was", "will", "with",
+];
+
+/// Only the lead of the body is compared; that is where wires and rewrites overlap.
+const BODY_WORDS: usize = 200;
+
+/// Stories about the same event, possibly from several sources.
+#[derive(Debug, Clone)]
+pub struct StoryCluster {
+    id: u64,
+    members: Vec<Member>,
+}
+
+#[derive(Debug, Clone)]
+struct Member {
+    article: Article,
+    title: HashSet<u64>,
+    body: HashSet<u64>,
+}
+
+impl StoryCluster {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The article that started the cluster, i.e. the first report of the event.
+    pub fn first(&self) -> &Article {
+        &self.members[0].article
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Every article in the cluster, in the order they were added.
+    pub fn articles(&self) -> impl Iterator<Item = &Article> {
+        self.members.iter().map(|member| &member.article)
+    }
+
+    /// Distinct sources in the order they picked up the story.
+    pub fn sources(&self) -> Vec<&str> {
+        let mut sources: Vec<&str> = Vec::new();
+        for article in self.articles() {
+            if !sources.contains(&article.source.as_str()) {
+                sources.push(&article.source);
+            }
+        }
+        sources
+    }
+
+    /// Publication time of the most recent article.
+    pub fn last_published_at(&self) -> DateTime<Utc> {
+        self.articles().map(|a| a.published_at).max().unwrap_or_default()
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.articles().any(|article| article.id == id)
+    }
+}
+
+/// Groups articles about the same event, so aggregation can count an event once
+/// however many sources report it.
+///
+/// An article joins the cluster holding its most similar story published within
+/// `window` of it, if the similarity reaches `threshold`. Similarity is the Jaccard
+/// index of word shingles, taken over the titles and over the bodies, whichever is
+/// higher. Headlines are short, so they are compared by word pairs at most.
+pub struct StoryClusterer {
+    threshold: f64,
+    window: Duration,
+    shingle_size: usize,
+    retention: Duration,
+    clusters: Vec<StoryCluster>,
+    next_id: u64,
+    latest: Option<DateTime<Utc>>,
+}
+
+impl Default for StoryClusterer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StoryClusterer {
+    pub fn new() -> Self {
+        Self {
+            threshold: 0.4,
+            window: Duration::hours(6),
+            shingle_size: 3,
+            retention: Duration::hours(24),
+            clusters: Vec::new(),
+            next_id: 0,
+            latest: None,
+        }
+    }
+
+    /// Minimum similarity, between 0 and 1, for two stories to be the same event.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+
+    /// How far apart in publication time two stories about the same event can be.
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Number of consecutive words per body shingle.
+    pub fn with_shingle_size(mut self, words: usize) -> Self {
+        self.shingle_size = words.max(1);
+        self
+    }
+
+    /// Clusters whose latest story is older than this, relative to the newest story
+    /// seen, are dropped.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Adds the article to the cluster of the event it reports, starting a new cluster
+    /// if there is none. Adding an article that is already clustered changes nothing.
+    pub fn add(&mut self, article: &Article) -> &StoryCluster {
+        let id = if article.id.is_empty() { article_id(&article.url) } else { article.id.clone() };
+        if let Some(index) = self.clusters.iter().position(|c| c.contains(&id)) {
+            return &self.clusters[index];
+        }
+
+        let mut article = article.clone();
+        article.id = id;
+        let member = Member {
+            title: shingles(&article.title, self.shingle_size.min(2), usize::MAX),
+            body: shingles(&article.body, self.shingle_size, BODY_WORDS),
+            article,
+        };
+
+        let published_at = member.article.published_at;
+        self.latest = Some(self.latest.map_or(published_at, |latest| latest.max(published_at)));
+        self.prune();
+
+        let best = self
+            .clusters
+            .iter()
+            .enumerate()
+            .filter_map(|(index, cluster)| {
+                cluster
+                    .members
+                    .iter()
+                    .filter(|other| (other.article.published_at - published_at).abs() <= self.window)
+                    .map(|other| similarity(&member, other))
+                    .reduce(f64::max)
+                    .map(|score| (index, score))
+            })
+            .filter(|(_, score)| *score >= self.threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        let index = match best {
+            Some((index, _)) => {
+                self.clusters[index].members.push(member);
+                index
+            }
+            None => {
+                self.clusters.push(StoryCluster { id: self.next_id, members: vec![member] });
+                self.next_id += 1;
+                self.clusters.len() - 1
+            }
+        };
+        &self.clusters[index]
+    }
+
+    /// The cluster holding the article with this id, see [`article_id`].
+    pub fn cluster_of(&self, id: &str) -> Option<&StoryCluster> {
+        self.clusters.iter().find(|cluster| cluster.contains(id))
+    }
+
+    pub fn clusters(&self) -> &[StoryCluster] {
+        &self.clusters
+    }
+
+    fn prune(&mut self) {
+        if let Some(latest) = self.latest {
+            let cutoff = latest - self.retention;
+            self.clusters.retain(|cluster| cluster.last_published_at() >= cutoff);
+        }
+    }
+}
+
+fn similarity(a: &Member, b: &Member) -> f64 {
+    jaccard(&a.title, &b.title).max(jaccard(&a.body, &b.body))
+}
+
+fn jaccard(a: &HashSet<u64>, b: &HashSet<u64>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+/// Hashes of the runs of `size` consecutive words among the first `limit` words of
+/// `text`, ignoring case, punctuation and stop words. Text shorter than `size` words
+/// is a single shingle.
+fn shingles(text: &str, size: usize, limit: usize) -> HashSet<u64> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .take(limit)
+        .collect();
+
+    let size = size.min(words.len()).max(1);
+    words
+        .windows(size)
+        .map(|window| {
+            let mut hasher = DefaultHasher::new();
+            window.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn article(source: &str, url: &str, title: &str, body: &str, hours: i64) -> Article {
+        Article {
+            id: article_id(url),
+            title: title.to_string(),
+            body: body.to_string(),
+            url: url.to_string(),
+            source: source.to_string(),
+            published_at: "2024-12-18T12:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::hours(hours),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shingles_ignore_case_punctuation_and_stop_words() {
+        assert_eq!(shingles("Fed holds rates", 2, usize::MAX), shingles("The FED holds... rates!", 2, usize::MAX));
+        assert_eq!(shingles("Fed holds rates steady", 2, usize::MAX).len(), 3);
+        assert_eq!(shingles("Fed", 3, usize::MAX).len(), 1);
+        assert_eq!(shingles("Fed holds rates steady", 2, 2).len(), 1);
+        assert!(shingles("the of and", 2, usize::MAX).is_empty());
+    }
+
+    #[test]
+    fn jaccard_of_shingle_sets() {
+        let a = shingles("fed holds rates steady", 1, usize::MAX);
+        let b = shingles("fed holds rates", 1, usize::MAX);
+        assert_eq!(jaccard(&a, &b), 0.75);
+        assert_eq!(jaccard(&a, &a), 1.0);
+        assert_eq!(jaccard(&a, &HashSet::new()), 0.0);
+    }
+
+    #[test]
+    fn clusters_rewrites_of_the_same_event() {
+        let mut clusterer = StoryClusterer::new();
+        let wire = article("Wire", "https://wire.example/fed", "Fed holds rates steady, signals fewer cuts", "", 0);
+        let rewrite = article("Daily", "https://daily.example/fed", "Fed Holds Rates Steady and Signals Fewer Cuts in 2025", "", 1);
+        let oil = article("Wire", "https://wire.example/oil", "Oil slips as OPEC delays output hike", "", 1);
+
+        let first = clusterer.add(&wire).id();
+        assert_eq!(clusterer.add(&rewrite).id(), first);
+        assert_ne!(clusterer.add(&oil).id(), first);
+        // Adding an article again changes nothing.
+        assert_eq!(clusterer.add(&wire).len(), 2);
+
+        let cluster = clusterer.cluster_of(&wire.id).unwrap();
+        assert_eq!(cluster.first().url, wire.url);
+        assert_eq!(cluster.sources(), ["Wire", "Daily"]);
+        assert_eq!(cluster.last_published_at(), rewrite.published_at);
+    }
+
+    #[test]
+    fn bodies_match_when_titles_differ() {
+        let lead = "The Federal Reserve left its benchmark rate unchanged on Wednesday while officials \
+                    penciled in fewer reductions for next year amid sticky inflation.";
+        let mut clusterer = StoryClusterer::new();
+        let a = clusterer.add(&article("Wire", "https://wire.example/a", "Powell keeps policy on hold", lead, 0)).id();
+        let b = clusterer.add(&article("Daily", "https://daily.example/b", "Markets digest Fed decision", lead, 0)).id();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn window_and_retention() {
+        let title = "Fed holds rates steady, signals fewer cuts";
+        let mut clusterer = StoryClusterer::new().with_window(Duration::hours(2)).with_retention(Duration::hours(10));
+        let a = clusterer.add(&article("Wire", "https://wire.example/1", title, "", 0)).id();
+        // Same headline, too late to be the same event.
+        let b = clusterer.add(&article("Wire", "https://wire.example/2", title, "", 5)).id();
+        assert_ne!(a, b);
+
+        clusterer.add(&article("Wire", "https://wire.example/3", "Oil slips", "", 12));
+        assert!(clusterer.cluster_of(&article_id("https://wire.example/1")).is_none());
+        assert!(clusterer.cluster_of(&article_id("https://wire.example/2")).is_some());
+    }
+
+    #[test]
+    fn threshold_is_respected() {
+        let mut clusterer = StoryClusterer::new().with_threshold(1.0);
+        let a = clusterer.add(&article("Wire", "https://wire.example/1", "Fed holds rates steady", "", 0)).id();
+        let b = clusterer.add(&article("Daily", "https://daily.example/1", "Fed holds rates steady again", "", 0)).id();
+        assert_ne!(a, b);
+    }
+}
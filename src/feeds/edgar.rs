@@ -0,0 +1,494 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::stream::{self, StreamExt};
+use roxmltree::Document;
+use scraper::{Html, Selector};
+use serde_json::Value;
+use super::base::{article_id, Article, BodyStatus, Feed, FetchReport, StoryError};
+use super::error::FeedError;
+use super::extract::{html_to_text, parse_date};
+use super::http::HttpClient;
+use super::xml::{child_text, descendant_text};
+
+const DEFAULT_BASE_URL: &str = "https://www.sec.gov";
+
+/// A filing listed in one of EDGAR's Atom feeds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filing {
+    pub form_type: String,
+    pub company: String,
+    pub cik: Option<String>,
+    pub accession_number: String,
+    /// The filing index page, which links every document of the filing.
+    pub index_url: String,
+    pub summary: String,
+    /// When the feed last updated the entry, or the filing date; `None` if it gave neither.
+    pub filed_at: Option<chrono::DateTime<Utc>>,
+}
+
+/// Which filings an [`EdgarFeed`] follows, e.g.
+/// `EdgarConfig::new("Bloomy admin@example.com").form_types(&["8-K", "S-1"]).ciks(&["1050446"])`.
+#[derive(Debug, Clone)]
+pub struct EdgarConfig {
+    base_url: String,
+    user_agent: String,
+    form_types: Vec<String>,
+    ciks: Vec<String>,
+    count: usize,
+    fetch_documents: bool,
+    max_body_chars: usize,
+    concurrency: usize,
+    request_timeout: Duration,
+}
+
+impl EdgarConfig {
+    /// SEC requires a user agent naming the requester and a contact address.
+    pub fn new(user_agent: &str) -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            user_agent: user_agent.to_string(),
+            form_types: vec!["8-K".to_string()],
+            ciks: Vec::new(),
+            count: 40,
+            fetch_documents: true,
+            max_body_chars: 50_000,
+            concurrency: 4,
+            request_timeout: Duration::from_secs(20),
+        }
+    }
+
+    /// Host serving `/cgi-bin/browse-edgar` and `/Archives`, e.g. a fixture server.
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn form_types(mut self, form_types: &[&str]) -> Self {
+        self.form_types = form_types.iter().map(|f| f.to_string()).collect();
+        self
+    }
+
+    /// Restricts the feed to these companies. Without CIKs the latest filings of all
+    /// companies are followed.
+    pub fn ciks(mut self, ciks: &[&str]) -> Self {
+        self.ciks = ciks.iter().map(|c| c.trim_start_matches('0').to_string()).collect();
+        self
+    }
+
+    /// Number of entries requested per form type and company.
+    pub fn count(mut self, count: usize) -> Self {
+        self.count = count.max(1);
+        self
+    }
+
+    /// Whether to download each filing's primary document into `Article::body`.
+    pub fn fetch_documents(mut self, fetch_documents: bool) -> Self {
+        self.fetch_documents = fetch_documents;
+        self
+    }
+
+    /// Longer documents are cut and flagged as truncated.
+    pub fn max_body_chars(mut self, max_body_chars: usize) -> Self {
+        self.max_body_chars = max_body_chars;
+        self
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// Makes archive links relative to the configured host, so fixtures served from
+    /// another host keep working when EDGAR returns absolute sec.gov links.
+    fn resolve_url(&self, url: &str) -> String {
+        if url.starts_with('/') {
+            format!("{}{}", self.base_url, url)
+        } else if let Some(path) = url.strip_prefix(DEFAULT_BASE_URL) {
+            format!("{}{}", self.base_url, path)
+        } else {
+            url.to_string()
+        }
+    }
+
+    fn feed_urls(&self) -> Vec<String> {
+        let mut urls = Vec::new();
+        for form_type in &self.form_types {
+            if self.ciks.is_empty() {
+                urls.push(format!(
+                    "{}/cgi-bin/browse-edgar?action=getcurrent&type={}&company=&dateb=&owner=include&start=0&count={}&output=atom",
+                    self.base_url, form_type, self.count
+                ));
+            }
+            for cik in &self.ciks {
+                urls.push(format!(
+                    "{}/cgi-bin/browse-edgar?action=getcompany&CIK={}&type={}&dateb=&owner=include&start=0&count={}&output=atom",
+                    self.base_url, cik, form_type, self.count
+                ));
+            }
+        }
+        urls
+    }
+}
+
+/// SEC EDGAR filings as articles: one article per new filing, with the primary
+/// document's text as the body.
+pub struct EdgarFeed {
+    http: HttpClient,
+    config: EdgarConfig,
+    seen: Mutex<HashSet<String>>,
+}
+
+impl EdgarFeed {
+    pub fn new(config: EdgarConfig) -> Self {
+        Self {
+            http: HttpClient::new(),
+            config,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Uses `http` for the listings and documents, e.g. one paced for SEC's limit of ten
+    /// requests per second with [`HttpClient::with_host_rate_limit`].
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Filings listed by the configured feeds that were not returned before, oldest first.
+    /// Feeds that cannot be read are skipped unless all of them fail.
+    pub async fn get_new_filings(&self) -> Result<Vec<Filing>, Box<dyn std::error::Error>> {
+        Ok(self.new_filings().await?.0)
+    }
+
+    /// Like [`EdgarFeed::get_new_filings`], but a filing feed that cannot be read is
+    /// reported in `failures` under its URL, as is each filing whose document fails.
+    pub async fn fetch_new_articles(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        let (filings, failures) = self.new_filings().await?;
+
+        let results: Vec<(String, Result<Article, StoryError>)> = stream::iter(filings)
+            .map(|filing| async move {
+                let accession_number = filing.accession_number.clone();
+                (accession_number, self.filing_article(filing).await)
+            })
+            .buffered(self.config.concurrency)
+            .collect()
+            .await;
+
+        let mut report = FetchReport { failures, ..Default::default() };
+        let mut seen = self.seen.lock().unwrap();
+        for (accession_number, result) in results {
+            match result {
+                Ok(article) => {
+                    seen.insert(accession_number);
+                    report.articles.push(article);
+                }
+                Err(failure) => report.failures.push(failure),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Unseen filings and the feeds that failed. Fails only if every feed failed.
+    async fn new_filings(&self) -> Result<(Vec<Filing>, Vec<StoryError>), FeedError> {
+        let urls = self.config.feed_urls();
+        let mut filings = Vec::new();
+        let mut failures = Vec::new();
+        for url in &urls {
+            let listed = match self.get(url).await {
+                Ok(xml) => Self::parse_filings(&xml).map_err(|e| FeedError::parse(url, e)),
+                Err(e) => Err(e),
+            };
+            match listed {
+                Ok(listed) => filings.extend(listed),
+                Err(e) => failures.push(StoryError::new(url, e)),
+            }
+        }
+        if !urls.is_empty() && failures.len() == urls.len() {
+            return Err(failures.swap_remove(0).error);
+        }
+
+        let seen = self.seen.lock().unwrap();
+        let mut accession_numbers = HashSet::new();
+        filings.retain(|filing| {
+            !seen.contains(&filing.accession_number) && accession_numbers.insert(filing.accession_number.clone())
+        });
+        filings.sort_by_key(|filing| filing.filed_at);
+        Ok((filings, failures))
+    }
+
+    /// Parses a `getcurrent` or `getcompany` Atom feed.
+    pub fn parse_filings(xml: &str) -> Result<Vec<Filing>, Box<dyn std::error::Error>> {
+        let document = Document::parse(xml)?;
+        let root = document.root_element();
+
+        // Company feeds name the company once, at the top.
+        let feed_company = descendant_text(root, "conformed-name");
+        let feed_cik = descendant_text(root, "cik");
+
+        let filings = root
+            .children()
+            .filter(|n| n.is_element() && n.tag_name().name() == "entry")
+            .filter_map(|entry| {
+                let title = child_text(entry, "title").unwrap_or_default();
+                let (title_form, title_company, title_cik) = parse_entry_title(&title);
+
+                let form_type = entry
+                    .children()
+                    .find(|n| n.is_element() && n.tag_name().name() == "category")
+                    .and_then(|n| n.attribute("term"))
+                    .map(str::to_string)
+                    .or_else(|| descendant_text(entry, "filing-type"))
+                    .or(title_form)?;
+                let index_url = entry
+                    .children()
+                    .find(|n| n.is_element() && n.tag_name().name() == "link")
+                    .and_then(|n| n.attribute("href"))
+                    .map(str::to_string)
+                    .or_else(|| descendant_text(entry, "filing-href"))?;
+                let accession_number = descendant_text(entry, "accession-number")
+                    .or_else(|| {
+                        child_text(entry, "id")
+                            .and_then(|id| id.split("accession-number=").nth(1).map(str::to_string))
+                    })
+                    .unwrap_or_else(|| index_url.clone());
+                let filed_at = child_text(entry, "updated")
+                    .or_else(|| descendant_text(entry, "filing-date"))
+                    .and_then(|date| parse_date(&date));
+                let summary = child_text(entry, "summary")
+                    .map(|summary| html_to_text(&summary))
+                    .unwrap_or_default();
+
+                Some(Filing {
+                    form_type,
+                    company: title_company.or_else(|| feed_company.clone()).unwrap_or_default(),
+                    cik: title_cik.or_else(|| feed_cik.clone()),
+                    accession_number,
+                    index_url,
+                    summary,
+                    filed_at,
+                })
+            })
+            .collect();
+
+        Ok(filings)
+    }
+
+    async fn filing_article(&self, filing: Filing) -> Result<Article, StoryError> {
+        let url = self.config.resolve_url(&filing.index_url);
+        let (body, body_status) = if self.config.fetch_documents {
+            let timeout = self.config.request_timeout;
+            let document = tokio::time::timeout(timeout, self.primary_document(&filing))
+                .await
+                .unwrap_or_else(|_| Err(FeedError::TimedOut { url: url.clone(), after: timeout }));
+            match document {
+                Ok(text) => self.limit_body(text),
+                Err(e) => return Err(StoryError::new(&url, e)),
+            }
+        } else if filing.summary.is_empty() {
+            (String::new(), BodyStatus::Missing)
+        } else {
+            (filing.summary.clone(), BodyStatus::Truncated)
+        };
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert("form_type".to_string(), Value::from(filing.form_type.clone()));
+        metadata.insert("accession_number".to_string(), Value::from(filing.accession_number));
+        if let Some(cik) = filing.cik {
+            metadata.insert("cik".to_string(), Value::from(cik));
+        }
+
+        let mut article = Article {
+            id: article_id(&url),
+            title: format!("{}: {}", filing.form_type, filing.company),
+            author: filing.company,
+            body,
+            summary: Some(filing.summary),
+            body_status,
+            url,
+            source: "SEC EDGAR".to_string(),
+            origin: None,
+            fetched_at: Some(Utc::now()),
+            language: Some("en".to_string()),
+            tags: vec![filing.form_type],
+            metadata,
+            ..Default::default()
+        };
+        article.set_published_at(filing.filed_at);
+        Ok(article)
+    }
+
+    fn limit_body(&self, text: String) -> (String, BodyStatus) {
+        match text.char_indices().nth(self.config.max_body_chars) {
+            Some((end, _)) => (text[..end].to_string(), BodyStatus::Truncated),
+            None => (text, BodyStatus::Full),
+        }
+    }
+
+    /// Text of the filing's primary document: the first document in the index whose
+    /// type matches the form type, or the first document listed.
+    async fn primary_document(&self, filing: &Filing) -> Result<String, FeedError> {
+        let index = self.get(&self.config.resolve_url(&filing.index_url)).await?;
+        let href = Self::primary_document_href(&index, &filing.form_type)
+            .ok_or_else(|| FeedError::parse(&filing.index_url, "primary document not found"))?;
+        let document = self.get(&self.config.resolve_url(&href)).await?;
+        Ok(html_to_text(&document))
+    }
+
+    fn primary_document_href(index_html: &str, form_type: &str) -> Option<String> {
+        let document = Html::parse_document(index_html);
+        let rows = Selector::parse("table.tableFile tr").unwrap();
+        let cells = Selector::parse("td").unwrap();
+        let link = Selector::parse("a[href]").unwrap();
+
+        let documents: Vec<(String, String)> = document
+            .select(&rows)
+            .filter_map(|row| {
+                let cells: Vec<_> = row.select(&cells).collect();
+                let href = cells.get(2)?.select(&link).next()?.value().attr("href")?;
+                let kind = cells.get(3).map(|c| c.text().collect::<String>().trim().to_string()).unwrap_or_default();
+                // Inline XBRL viewer links wrap the actual document path.
+                Some((href.trim_start_matches("/ix?doc=").to_string(), kind))
+            })
+            .collect();
+
+        documents
+            .iter()
+            .find(|(_, kind)| kind.eq_ignore_ascii_case(form_type))
+            .or(documents.first())
+            .map(|(href, _)| href.clone())
+    }
+
+    async fn get(&self, url: &str) -> Result<String, FeedError> {
+        let request = self.http
+            .get(url)
+            .header("user-agent", &self.config.user_agent);
+        Ok(self.http.fetch(request).await?.body)
+    }
+}
+
+/// Splits titles such as `8-K - MicroStrategy Inc (0001050446) (Filer)`.
+fn parse_entry_title(title: &str) -> (Option<String>, Option<String>, Option<String>) {
+    let Some((form, rest)) = title.split_once(" - ") else {
+        return (None, None, None);
+    };
+    let rest = rest.trim().trim_end_matches("(Filer)").trim_end_matches("(Subject)").trim();
+    let (company, cik) = match rest.rsplit_once(" (") {
+        Some((company, cik)) if cik.ends_with(')') && cik[..cik.len() - 1].chars().all(|c| c.is_ascii_digit()) => {
+            (company.trim().to_string(), Some(cik[..cik.len() - 1].trim_start_matches('0').to_string()))
+        }
+        _ => (rest.to_string(), None),
+    };
+    let company = (!company.is_empty()).then_some(company);
+    (Some(form.trim().to_string()), company, cik)
+}
+
+#[async_trait]
+impl Feed for EdgarFeed {
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        Ok(self.fetch_new_articles().await?.articles)
+    }
+
+    async fn fetch_report(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        self.fetch_new_articles().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use super::*;
+    use crate::feeds::http::RetryPolicy;
+    use crate::feeds::test_util::{respond, serve};
+
+    const CURRENT_8K: &str = r#"<?xml version="1.0" encoding="ISO-8859-1" ?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Latest Filings</title>
+  <entry>
+    <title>8-K - MicroStrategy Inc (0001050446) (Filer)</title>
+    <link rel="alternate" type="text/html" href="/Archives/edgar/data/1050446/000095017024000001-index.htm"/>
+    <summary type="html">&lt;b&gt;Filed:&lt;/b&gt; 2024-12-16 &lt;b&gt;AccNo:&lt;/b&gt; 0000950170-24-000001</summary>
+    <updated>2024-12-16T08:05:12-05:00</updated>
+    <category scheme="https://www.sec.gov/" label="form type" term="8-K"/>
+    <id>urn:tag:sec.gov,2008:accession-number=0000950170-24-000001</id>
+  </entry>
+</feed>"#;
+
+    /// Serves [`CURRENT_8K`] for 8-K listings and 404 for everything else.
+    async fn fixture_server() -> String {
+        serve(|request| {
+            if request.uri().query().unwrap_or_default().contains("type=8-K") {
+                respond(200, &[("content-type", "application/atom+xml")], CURRENT_8K)
+            } else {
+                respond(404, &[], "")
+            }
+        })
+        .await
+    }
+
+    fn feed(base_url: &str, form_types: &[&str]) -> EdgarFeed {
+        let config = EdgarConfig::new("Bloomy test@example.com")
+            .base_url(base_url)
+            .form_types(form_types)
+            .fetch_documents(false);
+        EdgarFeed::new(config).with_http_client(HttpClient::new().with_retry_policy(RetryPolicy::none()))
+    }
+
+    #[test]
+    fn parses_current_filings() {
+        let filings = EdgarFeed::parse_filings(CURRENT_8K).unwrap();
+        assert_eq!(filings.len(), 1);
+        assert_eq!(filings[0].form_type, "8-K");
+        assert_eq!(filings[0].company, "MicroStrategy Inc");
+        assert_eq!(filings[0].cik.as_deref(), Some("1050446"));
+        assert_eq!(filings[0].accession_number, "0000950170-24-000001");
+        assert_eq!(filings[0].filed_at, Some("2024-12-16T13:05:12Z".parse::<DateTime<Utc>>().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn undated_filings_are_flagged() {
+        let undated = CURRENT_8K.replace("<updated>2024-12-16T08:05:12-05:00</updated>", "");
+        let filing = EdgarFeed::parse_filings(&undated).unwrap().remove(0);
+        assert_eq!(filing.filed_at, None);
+
+        let article = feed(DEFAULT_BASE_URL, &["8-K"]).filing_article(filing).await.unwrap();
+        assert!(article.published_at_is_estimated());
+        assert_eq!(Some(article.published_at), article.fetched_at);
+    }
+
+    #[tokio::test]
+    async fn failing_form_type_does_not_hide_the_others() {
+        let base_url = fixture_server().await;
+        let feed = feed(&base_url, &["S-1", "8-K"]);
+
+        let report = feed.fetch_new_articles().await.unwrap();
+        assert_eq!(report.articles.len(), 1);
+        assert_eq!(report.articles[0].title, "8-K: MicroStrategy Inc");
+        let url = format!("{}/Archives/edgar/data/1050446/000095017024000001-index.htm", base_url);
+        assert_eq!(report.articles[0].url, url);
+        assert_eq!(report.articles[0].id, article_id(&url));
+        assert_eq!(report.articles[0].body_status, BodyStatus::Truncated);
+        assert_eq!(report.failures.len(), 1);
+        assert!(report.failures[0].url.contains("type=S-1"));
+        assert!(matches!(report.failures[0].error, FeedError::Status { status: reqwest::StatusCode::NOT_FOUND, .. }));
+
+        // Nothing new the second time, and the S-1 listing is still reported.
+        let report = feed.fetch_new_articles().await.unwrap();
+        assert!(report.articles.is_empty());
+        assert_eq!(report.failures.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn fails_when_every_form_type_fails() {
+        let base_url = fixture_server().await;
+        let error = feed(&base_url, &["S-1", "10-K"]).fetch_new_articles().await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<FeedError>(), Some(FeedError::Status { status: reqwest::StatusCode::NOT_FOUND, .. })));
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+
+/// Why fetching from a source failed. Feeds return it boxed, so callers can tell the
+/// cases apart with `error.downcast_ref::<FeedError>()`.
+#[derive(Debug)]
+pub enum FeedError {
+    /// The server asked us to slow down, with a 429 or a 503 carrying `Retry-After`.
+    RateLimited { url: String, retry_after: Option<Duration> },
+    /// The server answered with a bot check, captcha or access-denied page.
+    Blocked { url: String, reason: String },
+    /// The page is behind a paywall and has no usable text.
+    Paywalled { url: String },
+    /// A redirect that was not followed, e.g. by a client with redirects disabled.
+    Redirected { url: String, location: Option<String> },
+    /// The feed was blocked repeatedly and makes no requests until `until`.
+    CoolingDown { until: DateTime<Utc> },
+    /// Any other unsuccessful HTTP status.
+    Status { url: String, status: StatusCode },
+    /// The response arrived but could not be understood.
+    Parse { url: String, message: String },
+    /// No complete answer within `after`, counting retries.
+    TimedOut { url: String, after: Duration },
+    /// Connection failures, timeouts and other transport errors.
+    Request(reqwest::Error),
+    /// A transport error from a client other than this crate's `reqwest`, e.g. the
+    /// Solana RPC client.
+    Unreachable { url: String, message: String },
+    /// An external command could not be started, failed or exited when it should not have.
+    Command { command: String, message: String },
+}
+
+impl FeedError {
+    pub fn parse<E: fmt::Display>(url: &str, error: E) -> Self {
+        FeedError::Parse { url: url.to_string(), message: error.to_string() }
+    }
+
+    /// Whether trying again later may succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            FeedError::RateLimited { .. }
+            | FeedError::CoolingDown { .. }
+            | FeedError::TimedOut { .. }
+            | FeedError::Unreachable { .. } => true,
+            FeedError::Status { status, .. } => matches!(
+                *status,
+                StatusCode::REQUEST_TIMEOUT
+                    | StatusCode::INTERNAL_SERVER_ERROR
+                    | StatusCode::BAD_GATEWAY
+                    | StatusCode::SERVICE_UNAVAILABLE
+                    | StatusCode::GATEWAY_TIMEOUT
+            ),
+            FeedError::Request(e) => e.is_timeout() || e.is_connect() || e.is_request(),
+            FeedError::Blocked { .. }
+            | FeedError::Paywalled { .. }
+            | FeedError::Redirected { .. }
+            | FeedError::Parse { .. }
+            | FeedError::Command { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for FeedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedError::RateLimited { url, retry_after: Some(retry_after) } => {
+                write!(f, "Rate limited by {} (retry after {:?})", url, retry_after)
+            }
+            FeedError::RateLimited { url, retry_after: None } => write!(f, "Rate limited by {}", url),
+            FeedError::Blocked { url, reason } => write!(f, "Blocked by {}: {}", url, reason),
+            FeedError::Paywalled { url } => write!(f, "Paywalled: {}", url),
+            FeedError::Redirected { url, location: Some(location) } => {
+                write!(f, "Redirected from {} to {}", url, location)
+            }
+            FeedError::Redirected { url, location: None } => write!(f, "Redirected from {}", url),
+            FeedError::CoolingDown { until } => write!(f, "Cooling down after repeated blocks until {}", until),
+            FeedError::Status { url, status } => write!(f, "HTTP status {} for url ({})", status, url),
+            FeedError::Parse { url, message } => write!(f, "Failed to parse {}: {}", url, message),
+            FeedError::TimedOut { url, after } => write!(f, "Timed out after {:?}: {}", after, url),
+            FeedError::Request(e) => write!(f, "{}", e),
+            FeedError::Unreachable { url, message } => write!(f, "Could not reach {}: {}", url, message),
+            FeedError::Command { command, message } => write!(f, "{}: {}", command, message),
+        }
+    }
+}
+
+impl std::error::Error for FeedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FeedError::Request(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for FeedError {
+    fn from(e: reqwest::Error) -> Self {
+        FeedError::Request(e)
+    }
+}
@@ -0,0 +1,431 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use chrono::Utc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+use super::base::{Article, Feed};
+use super::error::FeedError;
+
+const MAX_MALFORMED_LINES: usize = 100;
+/// How long the output of a stopped command is read. A process the command left behind,
+/// e.g. one it started in the background, can hold stdout open indefinitely.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How an [`ExecFeed`] runs its command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecMode {
+    /// The command keeps running and prints articles as it finds them. It is restarted
+    /// when it exits or stays silent for longer than the timeout.
+    Persistent,
+    /// The command runs to completion on every poll and is killed after the timeout.
+    PerPoll,
+}
+
+/// A stdout line that was not a valid article.
+#[derive(Debug, Clone)]
+pub struct MalformedLine {
+    pub line: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default)]
+struct Output {
+    articles: Vec<Article>,
+    malformed: VecDeque<MalformedLine>,
+    stderr: VecDeque<String>,
+    last_output: Option<Instant>,
+}
+
+struct Running {
+    child: Child,
+    readers: Vec<JoinHandle<()>>,
+}
+
+/// Feed backed by an external process that prints one JSON-encoded article per line
+/// on stdout, so sources scraped by other tools can be ingested without a Rust module.
+pub struct ExecFeed {
+    program: String,
+    args: Vec<String>,
+    envs: Vec<(String, String)>,
+    current_dir: Option<PathBuf>,
+    source: Option<String>,
+    mode: ExecMode,
+    timeout: Duration,
+    max_restarts: Option<u32>,
+    restart_delay: Duration,
+    stderr_lines: usize,
+    output: Arc<Mutex<Output>>,
+    running: tokio::sync::Mutex<Option<Running>>,
+    restarts: Mutex<(u32, Option<Instant>)>,
+}
+
+impl ExecFeed {
+    pub fn new(program: &str) -> Self {
+        Self {
+            program: program.to_string(),
+            args: Vec::new(),
+            envs: Vec::new(),
+            current_dir: None,
+            source: None,
+            mode: ExecMode::Persistent,
+            timeout: Duration::from_secs(300),
+            max_restarts: None,
+            restart_delay: Duration::from_secs(5),
+            stderr_lines: 100,
+            output: Arc::new(Mutex::new(Output::default())),
+            running: tokio::sync::Mutex::new(None),
+            restarts: Mutex::new((0, None)),
+        }
+    }
+
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    pub fn args(mut self, args: &[&str]) -> Self {
+        self.args.extend(args.iter().map(|a| a.to_string()));
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.envs.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn current_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Source set on articles that do not name one.
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    pub fn with_mode(mut self, mode: ExecMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// For [`ExecMode::PerPoll`], how long one run may take. For
+    /// [`ExecMode::Persistent`], how long the command may go without printing anything
+    /// before it is considered hung and restarted.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Number of times a persistent command is restarted before the feed gives up.
+    /// Unlimited by default.
+    pub fn with_max_restarts(mut self, max_restarts: Option<u32>) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    /// Minimum time between two starts of a persistent command.
+    pub fn with_restart_delay(mut self, restart_delay: Duration) -> Self {
+        self.restart_delay = restart_delay;
+        self
+    }
+
+    /// Number of recent stderr lines kept for [`ExecFeed::stderr`].
+    pub fn with_stderr_lines(mut self, stderr_lines: usize) -> Self {
+        self.stderr_lines = stderr_lines;
+        self
+    }
+
+    /// Most recent lines the command wrote to stderr, oldest first.
+    pub fn stderr(&self) -> Vec<String> {
+        self.output.lock().unwrap().stderr.iter().cloned().collect()
+    }
+
+    /// Takes the stdout lines that could not be parsed as articles since the last call.
+    pub fn take_malformed_lines(&self) -> Vec<MalformedLine> {
+        self.output.lock().unwrap().malformed.drain(..).collect()
+    }
+
+    /// Number of times the persistent command has been restarted.
+    pub fn restarts(&self) -> u32 {
+        self.restarts.lock().unwrap().0
+    }
+
+    /// Kills the command if it is running.
+    pub async fn stop(&self) {
+        if let Some(mut running) = self.running.lock().await.take() {
+            let _ = running.child.kill().await;
+        }
+    }
+
+    fn spawn(&self) -> std::io::Result<Running> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .envs(self.envs.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+
+        let mut child = command.spawn()?;
+        self.output.lock().unwrap().last_output = Some(Instant::now());
+
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            let output = self.output.clone();
+            let source = self.source.clone();
+            readers.push(spawn_reader(stdout, move |line| {
+                output.lock().unwrap().push_stdout(line, source.as_deref());
+            }));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            let output = self.output.clone();
+            let capacity = self.stderr_lines;
+            readers.push(spawn_reader(stderr, move |line| {
+                output.lock().unwrap().push_stderr(line, capacity);
+            }));
+        }
+
+        Ok(Running { child, readers })
+    }
+
+    fn error(&self, message: String) -> FeedError {
+        FeedError::Command { command: self.program.clone(), message }
+    }
+
+    async fn poll_persistent(&self) -> Result<(), FeedError> {
+        let mut running = self.running.lock().await;
+
+        let idle = self
+            .output
+            .lock()
+            .unwrap()
+            .last_output
+            .is_some_and(|last| last.elapsed() > self.timeout);
+        if let Some(current) = running.as_mut() {
+            let exited = current.child.try_wait().map_err(|e| self.error(e.to_string()))?;
+            if exited.is_none() && !idle {
+                return Ok(());
+            }
+            if exited.is_none() {
+                let _ = current.child.kill().await;
+            }
+            drain(current.readers.drain(..).collect()).await;
+        }
+
+        let mut restarts = self.restarts.lock().unwrap();
+        let (count, last_start) = *restarts;
+        if running.is_some() {
+            if self.max_restarts.is_some_and(|max| count >= max) {
+                return Err(self.error(format!("stopped and reached the restart limit of {}", count)));
+            }
+            if last_start.is_some_and(|start| start.elapsed() < self.restart_delay) {
+                return Ok(());
+            }
+            restarts.0 += 1;
+        } else if last_start.is_some_and(|start| start.elapsed() < self.restart_delay) {
+            return Ok(());
+        }
+
+        restarts.1 = Some(Instant::now());
+        drop(restarts);
+        *running = Some(self.spawn().map_err(|e| self.error(format!("failed to start: {}", e)))?);
+        Ok(())
+    }
+
+    /// Runs the command to completion. Failing to exit successfully within the timeout
+    /// is an error.
+    async fn poll_once(&self) -> Result<(), FeedError> {
+        let Running { mut child, readers } = self.spawn().map_err(|e| self.error(format!("failed to start: {}", e)))?;
+
+        let status: Option<ExitStatus> = match tokio::time::timeout(self.timeout, child.wait()).await {
+            Ok(status) => Some(status.map_err(|e| self.error(e.to_string()))?),
+            Err(_) => {
+                let _ = child.kill().await;
+                None
+            }
+        };
+        drain(readers).await;
+        match status {
+            Some(status) if status.success() => Ok(()),
+            Some(status) => Err(self.error(format!("exited with {}", status))),
+            None => Err(FeedError::TimedOut { url: self.program.clone(), after: self.timeout }),
+        }
+    }
+}
+
+impl Output {
+    fn push_stdout(&mut self, line: String, source: Option<&str>) {
+        self.last_output = Some(Instant::now());
+        if line.trim().is_empty() {
+            return;
+        }
+        match serde_json::from_str::<Article>(&line) {
+            Ok(mut article) => {
+                if article.source.is_empty() {
+                    article.source = source.unwrap_or_default().to_string();
+                }
+                article.fetched_at.get_or_insert_with(Utc::now);
+                self.articles.push(article);
+            }
+            Err(e) => {
+                self.malformed.push_back(MalformedLine { line, error: e.to_string() });
+                while self.malformed.len() > MAX_MALFORMED_LINES {
+                    self.malformed.pop_front();
+                }
+            }
+        }
+    }
+
+    fn push_stderr(&mut self, line: String, capacity: usize) {
+        self.last_output = Some(Instant::now());
+        self.stderr.push_back(line);
+        while self.stderr.len() > capacity {
+            self.stderr.pop_front();
+        }
+    }
+}
+
+/// Lets the readers hand over whatever the command printed before it stopped, giving up
+/// on them after [`DRAIN_TIMEOUT`].
+async fn drain(readers: Vec<JoinHandle<()>>) {
+    let deadline = tokio::time::Instant::now() + DRAIN_TIMEOUT;
+    for reader in readers {
+        let abort = reader.abort_handle();
+        if tokio::time::timeout_at(deadline, reader).await.is_err() {
+            abort.abort();
+        }
+    }
+}
+
+fn spawn_reader<R, F>(stream: R, mut on_line: F) -> JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    F: FnMut(String) + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            on_line(line);
+        }
+    })
+}
+
+#[async_trait]
+impl Feed for ExecFeed {
+    /// Returns the articles printed since the last poll. In [`ExecMode::PerPoll`] a run
+    /// that times out or fails only errors when it produced no articles at all.
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        let problem = match self.mode {
+            ExecMode::Persistent => self.poll_persistent().await.err(),
+            ExecMode::PerPoll => self.poll_once().await.err(),
+        };
+
+        let articles = std::mem::take(&mut self.output.lock().unwrap().articles);
+        match problem {
+            Some(error) if articles.is_empty() => Err(Box::new(error)),
+            _ => Ok(articles),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = r#"{"title": "Fed Holds Rates Steady", "author": "", "body": "", "url": "https://example.com/fed", "source": "", "published_at": "2024-12-18T19:00:00Z"}"#;
+
+    #[tokio::test]
+    async fn background_process_holding_stdout_does_not_hang_the_poll() {
+        let script = format!("echo '{}'; sleep 3 &", ARTICLE);
+        let feed = ExecFeed::new("sh")
+            .args(&["-c", &script])
+            .with_mode(ExecMode::PerPoll)
+            .with_source("script");
+
+        let started = Instant::now();
+        let articles = feed.get_new_articles().await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(2900), "waited for the background process");
+        assert_eq!(articles.len(), 1);
+        assert_eq!(articles[0].source, "script");
+    }
+
+    fn script(script: &str) -> ExecFeed {
+        ExecFeed::new("sh").args(&["-c", script])
+    }
+
+    #[tokio::test]
+    async fn persistent_command_is_restarted_up_to_the_limit() {
+        let feed = script(&format!("echo '{}'", ARTICLE))
+            .with_restart_delay(Duration::ZERO)
+            .with_max_restarts(Some(2));
+
+        let mut articles = 0;
+        let error = loop {
+            match feed.get_new_articles().await {
+                Ok(new) => articles += new.len(),
+                Err(error) => break error,
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        };
+        assert_eq!(articles, 3);
+        assert_eq!(feed.restarts(), 2);
+        assert!(matches!(error.downcast_ref::<FeedError>(), Some(FeedError::Command { message, .. }) if message.contains("restart limit")));
+    }
+
+    #[tokio::test]
+    async fn keeps_the_latest_stderr_lines() {
+        let feed = script("echo one >&2; echo two >&2; echo three >&2; exit 3")
+            .with_mode(ExecMode::PerPoll)
+            .with_stderr_lines(2);
+
+        let error = feed.get_new_articles().await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<FeedError>(), Some(FeedError::Command { message, .. }) if message.contains("exit status: 3")));
+        assert_eq!(feed.stderr(), ["two", "three"]);
+    }
+
+    #[tokio::test]
+    async fn times_out_slow_and_silent_commands() {
+        let feed = script("sleep 5").with_mode(ExecMode::PerPoll).with_timeout(Duration::from_millis(200));
+        let started = Instant::now();
+        let error = feed.get_new_articles().await.unwrap_err();
+        // Killed rather than waited for; only the output of what it left behind is drained for a while.
+        assert!(started.elapsed() < DRAIN_TIMEOUT + Duration::from_secs(1));
+        assert!(matches!(error.downcast_ref::<FeedError>(), Some(FeedError::TimedOut { .. })));
+
+        // A persistent command that stays silent for longer than the timeout is restarted.
+        let feed = script(&format!("echo '{}'; sleep 5", ARTICLE))
+            .with_timeout(Duration::from_millis(200))
+            .with_restart_delay(Duration::ZERO);
+        feed.get_new_articles().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(feed.get_new_articles().await.unwrap().len(), 1);
+        assert_eq!(feed.restarts(), 0);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        feed.get_new_articles().await.unwrap();
+        assert_eq!(feed.restarts(), 1);
+        feed.stop().await;
+    }
+
+    #[tokio::test]
+    async fn malformed_lines_are_kept_aside() {
+        let feed = script(&format!("echo 'not json'; echo '{}'; echo '{{\"title\": 1}}'", ARTICLE))
+            .with_mode(ExecMode::PerPoll);
+
+        assert_eq!(feed.get_new_articles().await.unwrap().len(), 1);
+        let malformed = feed.take_malformed_lines();
+        let lines: Vec<&str> = malformed.iter().map(|malformed| malformed.line.as_str()).collect();
+        assert_eq!(lines, ["not json", r#"{"title": 1}"#]);
+        assert!(malformed.iter().all(|malformed| !malformed.error.is_empty()));
+        assert!(feed.take_malformed_lines().is_empty());
+    }
+}
@@ -0,0 +1,865 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LOCATION, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use super::error::FeedError;
+
+type HttpResult<T> = Result<T, FeedError>;
+
+// Markers of bot checks served in place of the requested page.
+const BLOCK_MARKERS: &[(&str, &str)] = &[
+    ("px-captcha", "PerimeterX captcha"),
+    ("unusual activity from your computer network", "PerimeterX captcha"),
+    ("/cdn-cgi/challenge-platform", "Cloudflare challenge"),
+    ("cf-chl-", "Cloudflare challenge"),
+];
+// Captcha widgets, which ordinary pages embed too, e.g. next to a comment or login form.
+const CAPTCHA_WIDGETS: &[(&str, &str)] = &[("g-recaptcha", "reCAPTCHA"), ("h-captcha", "hCaptcha")];
+const BLOCK_TITLES: &[&str] = &["are you a robot", "captcha", "access denied", "attention required", "just a moment"];
+// Bot check pages are small; a larger successful page is the real one.
+const MAX_INTERSTITIAL_LEN: usize = 64 * 1024;
+// Responses remembered per session unless set with `HttpClient::with_session_entries`.
+const SESSION_ENTRIES: usize = 10_000;
+
+/// How failed requests are retried. Only transient failures are retried, see
+/// [`FeedError::is_transient`]; the delay doubles after every attempt, with jitter, and a
+/// `Retry-After` from the server takes precedence.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Gives up after the first failure.
+    pub fn none() -> Self {
+        Self::default().max_retries(0)
+    }
+
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Longest wait between attempts. A `Retry-After` beyond it is not waited for and
+    /// the rate limit error is returned instead.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Delay before retry number `attempt` (starting at 0): half the exponential
+    /// delay plus a random share of the other half.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = delay / 2;
+        half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+}
+
+/// How a response was obtained.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheOutcome {
+    /// Served from the cache without a request.
+    Hit,
+    /// The server answered `304 Not Modified` and the cached body was reused.
+    Revalidated,
+    /// Downloaded.
+    Miss,
+}
+
+/// Counters of a [`HttpClient`] since it was created, shared by its clones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub revalidated: u64,
+    pub misses: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    /// Final URL after redirects.
+    pub url: String,
+    pub status: StatusCode,
+    pub body: String,
+    pub cache: CacheOutcome,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: DateTime<Utc>,
+    /// `None` in memory when the body lives in the disk cache.
+    body: Option<String>,
+}
+
+/// Responses seen this session, by request URL, evicting the least recently used.
+#[derive(Default)]
+struct Session {
+    entries: HashMap<String, (Entry, u64)>,
+    clock: u64,
+}
+
+impl Session {
+    fn get(&mut self, key: &str) -> Option<Entry> {
+        self.clock += 1;
+        let (entry, used) = self.entries.get_mut(key)?;
+        *used = self.clock;
+        Some(entry.clone())
+    }
+
+    fn insert(&mut self, key: &str, entry: Entry, capacity: usize) {
+        self.clock += 1;
+        self.entries.insert(key.to_string(), (entry, self.clock));
+        while self.entries.len() > capacity {
+            let Some(oldest) = self.entries.iter().min_by_key(|(_, (_, used))| *used).map(|(key, _)| key.clone()) else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+}
+
+#[derive(Default)]
+struct CacheState {
+    session: Mutex<Session>,
+    hits: AtomicU64,
+    revalidated: AtomicU64,
+    misses: AtomicU64,
+    // Earliest time the next request may be sent, by host.
+    next_request: Mutex<HashMap<String, Instant>>,
+}
+
+/// HTTP client shared by the feeds. Remembers `ETag` and `Last-Modified` validators so
+/// repeated polls become conditional GETs, can keep pages in a disk cache, paces
+/// requests per host and retries transient failures. 4xx and 5xx responses are
+/// returned as [`FeedError`]s.
+///
+/// Clones share the cache, its counters and the per-host pacing.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    cache_dir: Option<PathBuf>,
+    ttl: Duration,
+    timeout: Duration,
+    retry: RetryPolicy,
+    min_interval: Duration,
+    host_intervals: HashMap<String, Duration>,
+    session_entries: usize,
+    state: Arc<CacheState>,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpClient {
+    pub fn new() -> Self {
+        Self::from_client(reqwest::Client::new())
+    }
+
+    pub fn from_client(client: reqwest::Client) -> Self {
+        Self {
+            client,
+            cache_dir: None,
+            ttl: Duration::from_secs(24 * 60 * 60),
+            timeout: Duration::from_secs(30),
+            retry: RetryPolicy::default(),
+            min_interval: Duration::ZERO,
+            host_intervals: HashMap::new(),
+            session_entries: SESSION_ENTRIES,
+            state: Arc::new(CacheState::default()),
+        }
+    }
+
+    /// Time allowed for a single attempt.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Minimum time between two requests to the same host. None by default.
+    pub fn with_rate_limit(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    /// Overrides the rate limit for one host, e.g. `www.sec.gov`.
+    pub fn with_host_rate_limit(mut self, host: &str, min_interval: Duration) -> Self {
+        self.host_intervals.insert(host.to_string(), min_interval);
+        self
+    }
+
+    /// Keeps responses fetched with [`HttpClient::fetch_cached`] in `dir`, where they are
+    /// served without a request for `ttl` and revalidated afterwards.
+    pub fn with_disk_cache<P: Into<PathBuf>>(mut self, dir: P, ttl: Duration) -> Self {
+        self.cache_dir = Some(dir.into());
+        self.ttl = ttl;
+        self
+    }
+
+    /// Number of responses remembered for the session, 10,000 by default. Beyond it the
+    /// least recently used are forgotten: their validators, and their bodies unless they
+    /// are in the disk cache.
+    pub fn with_session_entries(mut self, session_entries: usize) -> Self {
+        self.session_entries = session_entries.max(1);
+        self
+    }
+
+    pub fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.state.hits.load(Ordering::Relaxed),
+            revalidated: self.state.revalidated.load(Ordering::Relaxed),
+            misses: self.state.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Sends the request, conditionally when an earlier response carried validators.
+    /// Suited to listings and feeds that must be polled for changes.
+    pub async fn fetch(&self, request: reqwest::RequestBuilder) -> HttpResult<HttpResponse> {
+        let request = request.build()?;
+        let key = request.url().to_string();
+        let cached = self.session_entry(&key).filter(|entry| entry.body.is_some());
+        self.send(request, cached, false).await
+    }
+
+    /// Serves the response from the cache when possible: anything fetched this session is
+    /// not downloaded again while it is among the remembered responses, see
+    /// [`HttpClient::with_session_entries`], and the disk cache is used until its TTL runs
+    /// out. Suited to pages that do not change once published, such as stories.
+    pub async fn fetch_cached(&self, request: reqwest::RequestBuilder) -> HttpResult<HttpResponse> {
+        let request = request.build()?;
+        let key = request.url().to_string();
+
+        let session = self.session_entry(&key);
+        let (cached, fresh) = match session {
+            Some(Entry { body: Some(body), .. }) => return Ok(self.hit(key, body)),
+            Some(_) => (self.read_disk(&key).await, true),
+            None => {
+                let entry = self.read_disk(&key).await;
+                let fresh = entry.as_ref().is_some_and(|entry| {
+                    (Utc::now() - entry.fetched_at).to_std().is_ok_and(|age| age < self.ttl)
+                });
+                (entry, fresh)
+            }
+        };
+
+        if let Some(entry) = cached.clone().filter(|_| fresh) {
+            let body = entry.body.clone().unwrap_or_default();
+            self.remember(&key, entry);
+            return Ok(self.hit(key, body));
+        }
+        self.send(request, cached, true).await
+    }
+
+    /// Always asks the server, conditionally when the page is cached, and caches the
+    /// response like [`HttpClient::fetch_cached`]. For cached pages known to have changed.
+    pub async fn fetch_revalidated(&self, request: reqwest::RequestBuilder) -> HttpResult<HttpResponse> {
+        let request = request.build()?;
+        let key = request.url().to_string();
+
+        let session = self.session_entry(&key);
+        let cached = match session {
+            Some(entry) if entry.body.is_some() => Some(entry),
+            _ => self.read_disk(&key).await,
+        };
+        self.send(request, cached, true).await
+    }
+
+    /// Sends the request, retrying transient failures according to the retry policy.
+    async fn send(&self, mut request: reqwest::Request, cached: Option<Entry>, keep: bool) -> HttpResult<HttpResponse> {
+        *request.timeout_mut() = Some(self.timeout);
+        if let Some(entry) = &cached {
+            if let Some(etag) = entry.etag.as_deref().and_then(|v| v.parse().ok()) {
+                request.headers_mut().insert(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = entry.last_modified.as_deref().and_then(|v| v.parse().ok()) {
+                request.headers_mut().insert(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let mut attempt = 0;
+        loop {
+            let Some(attempt_request) = request.try_clone() else {
+                return self.send_once(request, cached, keep).await;
+            };
+            let error = match self.send_once(attempt_request, cached.clone(), keep).await {
+                Ok(response) => return Ok(response),
+                Err(error) => error,
+            };
+            if attempt >= self.retry.max_retries || !error.is_transient() {
+                return Err(error);
+            }
+
+            let delay = match &error {
+                FeedError::RateLimited { retry_after: Some(retry_after), .. } => {
+                    if *retry_after > self.retry.max_delay {
+                        return Err(error);
+                    }
+                    *retry_after
+                }
+                _ => self.retry.backoff(attempt),
+            };
+            if matches!(error, FeedError::RateLimited { .. }) {
+                // Hold back every request to the host, not only this one.
+                self.defer_host(request.url(), delay);
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send_once(&self, request: reqwest::Request, cached: Option<Entry>, keep: bool) -> HttpResult<HttpResponse> {
+        let key = request.url().to_string();
+        self.wait_for_host(request.url()).await;
+
+        let response = self.client.execute(request).await?;
+        let status = response.status();
+        let url = response.url().to_string();
+
+        if status == StatusCode::NOT_MODIFIED {
+            if let Some(mut entry) = cached {
+                self.state.revalidated.fetch_add(1, Ordering::Relaxed);
+                let body = entry.body.clone().unwrap_or_default();
+                entry.fetched_at = Utc::now();
+                self.store(&key, entry, keep).await;
+                return Ok(HttpResponse { url, status: StatusCode::OK, body, cache: CacheOutcome::Revalidated });
+            }
+        }
+
+        if status.is_redirection() && status != StatusCode::NOT_MODIFIED {
+            let location = response
+                .headers()
+                .get(LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .map(|location| response.url().join(location).map(|l| l.to_string()).unwrap_or(location.to_string()));
+            return Err(FeedError::Redirected { url, location });
+        }
+
+        let retry_after = retry_after(response.headers());
+        if status == StatusCode::TOO_MANY_REQUESTS || (status == StatusCode::SERVICE_UNAVAILABLE && retry_after.is_some()) {
+            return Err(FeedError::RateLimited { url, retry_after });
+        }
+
+        let header = |name| response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = response.text().await?;
+
+        if let Some(reason) = block_reason(status, &body) {
+            return Err(FeedError::Blocked { url, reason });
+        }
+        if status.is_client_error() || status.is_server_error() {
+            return Err(FeedError::Status { url, status });
+        }
+        self.state.misses.fetch_add(1, Ordering::Relaxed);
+
+        if status.is_success() && (keep || etag.is_some() || last_modified.is_some()) {
+            let entry = Entry {
+                url: key.clone(),
+                etag,
+                last_modified,
+                fetched_at: Utc::now(),
+                body: Some(body.clone()),
+            };
+            self.store(&key, entry, keep).await;
+        }
+        Ok(HttpResponse { url, status, body, cache: CacheOutcome::Miss })
+    }
+
+    /// Waits for the host's next free slot and reserves the one after it.
+    async fn wait_for_host(&self, url: &reqwest::Url) {
+        let Some(host) = url.host_str() else {
+            return;
+        };
+        let interval = self.host_intervals.get(host).copied().unwrap_or(self.min_interval);
+        let wait = {
+            let mut next_request = self.state.next_request.lock().unwrap();
+            let now = Instant::now();
+            let slot = next_request.get(host).copied().unwrap_or(now).max(now);
+            next_request.insert(host.to_string(), slot + interval);
+            slot - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn defer_host(&self, url: &reqwest::Url, delay: Duration) {
+        if let Some(host) = url.host_str() {
+            let until = Instant::now() + delay;
+            let mut next_request = self.state.next_request.lock().unwrap();
+            let slot = next_request.entry(host.to_string()).or_insert(until);
+            *slot = (*slot).max(until);
+        }
+    }
+
+    fn hit(&self, url: String, body: String) -> HttpResponse {
+        self.state.hits.fetch_add(1, Ordering::Relaxed);
+        HttpResponse { url, status: StatusCode::OK, body, cache: CacheOutcome::Hit }
+    }
+
+    /// Records the entry for the session and, for cached fetches, on disk.
+    async fn store(&self, key: &str, entry: Entry, keep: bool) {
+        if keep {
+            if let Some(path) = self.disk_path(key) {
+                if let Ok(json) = serde_json::to_vec(&entry) {
+                    // A failed write only costs a download next session.
+                    if let Some(dir) = path.parent() {
+                        let _ = tokio::fs::create_dir_all(dir).await;
+                    }
+                    if tokio::fs::write(&path, json).await.is_ok() {
+                        self.remember(key, entry);
+                        return;
+                    }
+                }
+            }
+        }
+        self.state.session.lock().unwrap().insert(key, entry, self.session_entries);
+    }
+
+    /// Records a disk-backed entry for the session without holding its body in memory.
+    fn remember(&self, key: &str, entry: Entry) {
+        let entry = Entry { body: None, ..entry };
+        self.state.session.lock().unwrap().insert(key, entry, self.session_entries);
+    }
+
+    fn session_entry(&self, key: &str) -> Option<Entry> {
+        self.state.session.lock().unwrap().get(key)
+    }
+
+    async fn read_disk(&self, key: &str) -> Option<Entry> {
+        let path = self.disk_path(key)?;
+        let json = tokio::fs::read(&path).await.ok()?;
+        serde_json::from_slice::<Entry>(&json)
+            .ok()
+            .filter(|entry| entry.url == key && entry.body.is_some())
+    }
+
+    fn disk_path(&self, key: &str) -> Option<PathBuf> {
+        self.cache_dir.as_deref().map(|dir| cache_file(dir, key))
+    }
+}
+
+fn cache_file(dir: &Path, url: &str) -> PathBuf {
+    let name: String = Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    dir.join(format!("{}.json", name))
+}
+
+/// `Retry-After` as either seconds or an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or_default())
+}
+
+/// Why the response looks like a bot check rather than the requested page. Successful
+/// responses only count when they look like an interstitial, see [`interstitial_reason`].
+fn block_reason(status: StatusCode, body: &str) -> Option<String> {
+    if status.is_success() {
+        return interstitial_reason(body);
+    }
+    if let Some((_, name)) = BLOCK_MARKERS.iter().chain(CAPTCHA_WIDGETS).find(|(marker, _)| body.contains(marker)) {
+        return Some(name.to_string());
+    }
+    if let Some(marker) = block_title(body) {
+        return Some(format!("page titled like a bot check ({})", marker));
+    }
+    (status == StatusCode::FORBIDDEN).then(|| "access denied (403)".to_string())
+}
+
+/// Why a page is a bot check served in place of the requested one: a small page titled
+/// like one, e.g. "Just a moment...", or carrying the markers of a challenge script.
+/// A captcha widget alone does not count.
+pub fn interstitial_reason(body: &str) -> Option<String> {
+    if body.len() > MAX_INTERSTITIAL_LEN {
+        return None;
+    }
+    if let Some(marker) = block_title(body) {
+        return Some(format!("page titled like a bot check ({})", marker));
+    }
+    BLOCK_MARKERS
+        .iter()
+        .find(|(marker, _)| body.contains(marker))
+        .map(|(_, name)| name.to_string())
+}
+
+/// The bot check phrase the page title consists of, apart from a site name such as
+/// "Attention Required! | Cloudflare".
+fn block_title(body: &str) -> Option<&'static str> {
+    let title = page_title(body).to_lowercase();
+    title
+        .split(" | ")
+        .flat_map(|part| part.split(" - "))
+        .flat_map(|part| part.split(" \u{2013} "))
+        .map(|part| part.trim_matches(|c: char| !c.is_alphanumeric()))
+        .find_map(|part| BLOCK_TITLES.iter().find(|marker| part == **marker).copied())
+}
+
+fn page_title(body: &str) -> &str {
+    let Some(start) = body.find("<title") else {
+        return "";
+    };
+    let rest = &body[start..];
+    let Some(open_end) = rest.find('>') else {
+        return "";
+    };
+    let rest = &rest[open_end + 1..];
+    rest.find("</title>").map(|end| &rest[..end]).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::test_util::{respond, serve, TempDir};
+
+    /// Serves every path with an `ETag`, or with only a `Last-Modified` date under
+    /// `/dated`, and answers conditional requests with a 304. `/missing` is a 404.
+    /// Returns the base URL and the requests received, conditional ones marked with `?`.
+    async fn page_server() -> (String, Arc<Mutex<Vec<String>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+        let base_url = serve(move |request| {
+            let path = request.uri().path().to_string();
+            let headers = request.headers();
+            let conditional = headers.contains_key("if-none-match") || headers.contains_key("if-modified-since");
+            log.lock().unwrap().push(format!("{}{}", path, if conditional { "?" } else { "" }));
+            if path == "/missing" {
+                return respond(404, &[], "<html></html>");
+            }
+            let validator = if path.starts_with("/dated") {
+                ("last-modified", "Mon, 06 Jan 2025 12:00:00 GMT")
+            } else {
+                ("etag", "\"v1\"")
+            };
+            if conditional {
+                return respond(304, &[validator], "");
+            }
+            respond(200, &[validator], &format!("page {}", path))
+        })
+        .await;
+        (base_url, requests)
+    }
+
+    fn stats(hits: u64, revalidated: u64, misses: u64) -> CacheStats {
+        CacheStats { hits, revalidated, misses }
+    }
+
+    #[tokio::test]
+    async fn repeated_fetches_are_conditional() {
+        let (base_url, requests) = page_server().await;
+        let client = HttpClient::new();
+
+        for path in ["/feed", "/dated/feed"] {
+            let url = format!("{}{}", base_url, path);
+            let first = client.fetch(client.get(&url)).await.unwrap();
+            let second = client.fetch(client.get(&url)).await.unwrap();
+            assert_eq!((first.cache, second.cache), (CacheOutcome::Miss, CacheOutcome::Revalidated));
+            assert_eq!(second.body, format!("page {}", path));
+        }
+        assert_eq!(*requests.lock().unwrap(), ["/feed", "/feed?", "/dated/feed", "/dated/feed?"]);
+        assert_eq!(client.stats(), stats(0, 2, 2));
+    }
+
+    #[tokio::test]
+    async fn cached_page_is_downloaded_once_per_session() {
+        let (base_url, requests) = page_server().await;
+        let client = HttpClient::new();
+        let url = format!("{}/story", base_url);
+
+        assert_eq!(client.fetch_cached(client.get(&url)).await.unwrap().cache, CacheOutcome::Miss);
+        let again = client.fetch_cached(client.get(&url)).await.unwrap();
+        assert_eq!(again.cache, CacheOutcome::Hit);
+        assert_eq!(again.body, "page /story");
+        // Clones share the session.
+        assert_eq!(client.clone().fetch_cached(client.get(&url)).await.unwrap().cache, CacheOutcome::Hit);
+        assert_eq!(*requests.lock().unwrap(), ["/story"]);
+        assert_eq!(client.stats(), stats(2, 0, 1));
+    }
+
+    #[tokio::test]
+    async fn disk_cache_is_revalidated_after_its_ttl() {
+        let (base_url, requests) = page_server().await;
+        let dir = TempDir::new();
+        let url = format!("{}/story", base_url);
+        let client = |ttl| HttpClient::new().with_disk_cache(&dir.0, ttl);
+
+        let first = client(Duration::from_secs(3600));
+        assert_eq!(first.fetch_cached(first.get(&url)).await.unwrap().cache, CacheOutcome::Miss);
+
+        // A new session reads the page from disk while it is fresh...
+        let fresh = client(Duration::from_secs(3600));
+        assert_eq!(fresh.fetch_cached(fresh.get(&url)).await.unwrap().cache, CacheOutcome::Hit);
+        assert_eq!(*requests.lock().unwrap(), ["/story"]);
+
+        // ...and asks the server once it expired.
+        let expired = client(Duration::ZERO);
+        let response = expired.fetch_cached(expired.get(&url)).await.unwrap();
+        assert_eq!(response.cache, CacheOutcome::Revalidated);
+        assert_eq!(response.body, "page /story");
+        assert_eq!(*requests.lock().unwrap(), ["/story", "/story?"]);
+        assert_eq!(expired.stats(), stats(0, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn error_responses_are_not_counted_as_misses() {
+        let (base_url, _) = page_server().await;
+        let client = HttpClient::new();
+        let error = client.fetch(client.get(&format!("{}/missing", base_url))).await.unwrap_err();
+        assert!(matches!(error, FeedError::Status { status: StatusCode::NOT_FOUND, .. }));
+        assert_eq!(client.stats(), CacheStats::default());
+    }
+
+    #[tokio::test]
+    async fn session_forgets_the_least_recently_used_pages() {
+        let (base_url, requests) = page_server().await;
+        let client = HttpClient::new().with_session_entries(2);
+        for path in ["/a", "/b", "/a", "/c", "/a", "/b"] {
+            client.fetch_cached(client.get(&format!("{}{}", base_url, path))).await.unwrap();
+        }
+        assert_eq!(*requests.lock().unwrap(), ["/a", "/b", "/c", "/b"]);
+        assert_eq!(client.stats(), stats(2, 0, 4));
+    }
+
+    /// Serves `/flaky` with two 503s, `/limited` with a 429 carrying `retry_after`, each
+    /// followed by a page, and `/missing` with a 404. Returns the base URL and the number
+    /// of requests per path.
+    async fn flaky_server(retry_after: &str) -> (String, Arc<Mutex<HashMap<String, usize>>>) {
+        let retry_after = retry_after.to_string();
+        let requests = Arc::new(Mutex::new(HashMap::new()));
+        let counts = requests.clone();
+        let base_url = serve(move |request| {
+            let path = request.uri().path().to_string();
+            let count = {
+                let mut counts = counts.lock().unwrap();
+                let count = counts.entry(path.clone()).or_insert(0);
+                *count += 1;
+                *count
+            };
+            match (path.as_str(), count) {
+                ("/flaky", 1..=2) => respond(503, &[], "<html></html>"),
+                ("/limited", 1) => respond(429, &[("retry-after", &retry_after)], ""),
+                ("/missing", _) => respond(404, &[], "<html></html>"),
+                _ => respond(200, &[], "ok"),
+            }
+        })
+        .await;
+        (base_url, requests)
+    }
+
+    fn quick_retries() -> RetryPolicy {
+        RetryPolicy::default().base_delay(Duration::from_millis(10)).max_delay(Duration::from_secs(5))
+    }
+
+    #[test]
+    fn backoff_doubles_within_jitter_bounds() {
+        let policy = RetryPolicy::default().base_delay(Duration::from_millis(100)).max_delay(Duration::from_millis(700));
+        for (attempt, full) in [(0, 100), (1, 200), (2, 400), (3, 700), (10, 700)] {
+            let full = Duration::from_millis(full);
+            let delays: Vec<Duration> = (0..200).map(|_| policy.backoff(attempt)).collect();
+            assert!(delays.iter().all(|delay| *delay >= full / 2 && *delay <= full), "attempt {}", attempt);
+            // Jittered, not a fixed delay.
+            assert!(delays.iter().any(|delay| *delay != delays[0]), "attempt {}", attempt);
+        }
+    }
+
+    #[test]
+    fn parses_retry_after_in_both_forms() {
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, value.parse().unwrap());
+            headers
+        };
+        assert_eq!(retry_after(&headers("120")), Some(Duration::from_secs(120)));
+
+        let date = (Utc::now() + chrono::Duration::seconds(90)).to_rfc2822().replace("+0000", "GMT");
+        let delay = retry_after(&headers(&date)).unwrap();
+        assert!(delay > Duration::from_secs(85) && delay <= Duration::from_secs(90), "{:?}", delay);
+
+        // A date in the past means right away.
+        assert_eq!(retry_after(&headers("Wed, 21 Oct 2015 07:28:00 GMT")), Some(Duration::ZERO));
+        assert_eq!(retry_after(&headers("soon")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures_only() {
+        let (base_url, requests) = flaky_server("0").await;
+        let client = HttpClient::new().with_retry_policy(quick_retries());
+
+        assert_eq!(client.fetch(client.get(&format!("{}/flaky", base_url))).await.unwrap().body, "ok");
+        assert_eq!(client.fetch(client.get(&format!("{}/limited", base_url))).await.unwrap().body, "ok");
+        let error = client.fetch(client.get(&format!("{}/missing", base_url))).await.unwrap_err();
+        assert!(matches!(error, FeedError::Status { status: StatusCode::NOT_FOUND, .. }));
+
+        let requests = requests.lock().unwrap();
+        assert_eq!((requests["/flaky"], requests["/limited"], requests["/missing"]), (3, 2, 1));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let (base_url, requests) = flaky_server("0").await;
+        let client = HttpClient::new().with_retry_policy(quick_retries().max_retries(1));
+        let error = client.fetch(client.get(&format!("{}/flaky", base_url))).await.unwrap_err();
+        assert!(matches!(error, FeedError::Status { status: StatusCode::SERVICE_UNAVAILABLE, .. }));
+        assert_eq!(requests.lock().unwrap()["/flaky"], 2);
+    }
+
+    #[tokio::test]
+    async fn waits_as_long_as_retry_after_says() {
+        let (base_url, _) = flaky_server("1").await;
+        let client = HttpClient::new().with_retry_policy(quick_retries());
+        let started = Instant::now();
+        client.fetch(client.get(&format!("{}/limited", base_url))).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_secs(1));
+
+        // Longer than the policy allows: the rate limit is returned instead of waiting.
+        let (base_url, requests) = flaky_server("60").await;
+        let started = Instant::now();
+        let error = client.fetch(client.get(&format!("{}/limited", base_url))).await.unwrap_err();
+        assert!(matches!(error, FeedError::RateLimited { retry_after: Some(after), .. } if after == Duration::from_secs(60)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(requests.lock().unwrap()["/limited"], 1);
+
+        let date = (Utc::now() + chrono::Duration::seconds(2)).to_rfc2822().replace("+0000", "GMT");
+        let (base_url, _) = flaky_server(&date).await;
+        let started = Instant::now();
+        client.fetch(client.get(&format!("{}/limited", base_url))).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(900), "{:?}", started.elapsed());
+    }
+
+    #[tokio::test]
+    async fn paces_requests_per_host() {
+        let (base_url, _) = flaky_server("0").await;
+        let url = format!("{}/page", base_url);
+
+        let client = HttpClient::new().with_rate_limit(Duration::from_millis(100));
+        let started = Instant::now();
+        for _ in 0..3 {
+            client.fetch(client.get(&url)).await.unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(200));
+
+        // The override for the host wins over the default.
+        let client = HttpClient::new()
+            .with_rate_limit(Duration::from_secs(10))
+            .with_host_rate_limit("127.0.0.1", Duration::ZERO);
+        let started = Instant::now();
+        for _ in 0..3 {
+            client.fetch(client.get(&url)).await.unwrap();
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn rate_limit_holds_back_the_whole_host() {
+        let (base_url, _) = flaky_server("1").await;
+        let client = HttpClient::new().with_retry_policy(quick_retries());
+        let limited = client.clone();
+        let limited_url = format!("{}/limited", base_url);
+        let retrying = tokio::spawn(async move { limited.fetch(limited.get(&limited_url)).await.map(|r| r.body) });
+
+        // Give the first request time to be rate limited, then ask for another page.
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let started = Instant::now();
+        client.fetch(client.get(&format!("{}/page", base_url))).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(600), "{:?}", started.elapsed());
+        assert_eq!(retrying.await.unwrap().unwrap(), "ok");
+    }
+
+    const ARTICLE_WITH_CAPTCHA_WIDGET: &str = r#"<!DOCTYPE html>
+<html><head><title>Fed Holds Rates Steady as Inflation Cools - Example News</title></head>
+<body>
+<article><h1>Fed Holds Rates Steady as Inflation Cools</h1>
+<p>The Federal Reserve left its benchmark rate unchanged on Wednesday.</p></article>
+<form id="comments"><div class="g-recaptcha" data-sitekey="6Lc_example"></div><button>Post</button></form>
+</body></html>"#;
+
+    #[test]
+    fn article_with_captcha_widget_is_not_blocked() {
+        assert_eq!(block_reason(StatusCode::OK, ARTICLE_WITH_CAPTCHA_WIDGET), None);
+    }
+
+    #[test]
+    fn article_about_captchas_is_not_blocked() {
+        let html = "<html><head><title>Why Captcha Farms Are Booming | Example News</title></head><body></body></html>";
+        assert_eq!(block_reason(StatusCode::OK, html), None);
+    }
+
+    #[test]
+    fn successful_interstitial_is_blocked() {
+        let html = r#"<html><head><title>Just a moment...</title></head>
+<body><script src="/cdn-cgi/challenge-platform/h/b/orchestrate/chl_page/v1"></script></body></html>"#;
+        assert!(block_reason(StatusCode::OK, html).is_some());
+
+        let html = r#"<html><head><title>Bloomberg - Are you a robot?</title></head><body></body></html>"#;
+        assert_eq!(
+            block_reason(StatusCode::OK, html).as_deref(),
+            Some("page titled like a bot check (are you a robot)")
+        );
+    }
+
+    #[test]
+    fn large_page_is_never_an_interstitial() {
+        let html = format!("<html><body><div id=\"px-captcha\"></div>{}</body></html>", "<p>text</p>".repeat(10_000));
+        assert_eq!(block_reason(StatusCode::OK, &html), None);
+    }
+
+    #[test]
+    fn error_response_with_captcha_is_blocked() {
+        assert_eq!(
+            block_reason(StatusCode::FORBIDDEN, ARTICLE_WITH_CAPTCHA_WIDGET).as_deref(),
+            Some("reCAPTCHA")
+        );
+        assert_eq!(
+            block_reason(StatusCode::FORBIDDEN, "<html></html>").as_deref(),
+            Some("access denied (403)")
+        );
+        assert_eq!(block_reason(StatusCode::NOT_FOUND, "<html></html>"), None);
+    }
+}
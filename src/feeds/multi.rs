@@ -0,0 +1,419 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures::future::join_all;
+use serde_json::Value;
+use super::base::{canonical_url, normalized_title, Article, BodyStatus, Feed, FetchReport, StoryError};
+use super::error::FeedError;
+
+const DEFAULT_MEMORY: usize = 10_000;
+
+// Metadata key of a source's update marker, as Bloomberg sets it.
+const UPDATED_AT_KEY: &str = "updated_at";
+
+/// A sub-feed that returned an error during a poll.
+#[derive(Debug)]
+pub struct FeedFailure {
+    pub feed: String,
+    /// The sub-feed's error, e.g. a [`FeedError`] to find with `downcast_ref`. Errors of
+    /// other types only keep their message.
+    pub error: Box<dyn Error + Send + Sync>,
+}
+
+impl fmt::Display for FeedFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.feed, self.error)
+    }
+}
+
+impl Error for FeedFailure {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.error.as_ref())
+    }
+}
+
+/// Returned by [`MultiFeed`] as a [`Feed`] when every sub-feed failed.
+#[derive(Debug)]
+pub struct AllFeedsFailed(pub Vec<FeedFailure>);
+
+impl fmt::Display for AllFeedsFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failures: Vec<String> = self.0.iter().map(|f| f.to_string()).collect();
+        write!(f, "All feeds failed: {}", failures.join("; "))
+    }
+}
+
+impl Error for AllFeedsFailed {}
+
+/// Outcome of one poll across every sub-feed.
+#[derive(Debug, Default)]
+pub struct MultiFeedReport {
+    pub articles: Vec<Article>,
+    /// Sub-feeds that failed as a whole.
+    pub failures: Vec<FeedFailure>,
+    /// Stories the sub-feeds failed to fetch while their poll succeeded.
+    pub story_failures: Vec<StoryError>,
+}
+
+/// Values by key, forgetting the oldest key once `capacity` is reached.
+#[derive(Debug)]
+struct Recent<V> {
+    values: HashMap<String, V>,
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl<V> Recent<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            values: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&V> {
+        self.values.get(key)
+    }
+
+    fn insert(&mut self, key: String, value: V) {
+        if self.values.insert(key.clone(), value).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.values.remove(&oldest);
+            }
+        }
+    }
+
+    fn retain(&mut self, mut keep: impl FnMut(&V) -> bool) {
+        self.values.retain(|_, value| keep(value));
+        let values = &self.values;
+        self.order.retain(|key| values.contains_key(key));
+    }
+}
+
+/// What was delivered for a URL, to tell a re-delivery from a newer version.
+#[derive(Debug, Clone, PartialEq)]
+struct Version {
+    completeness: u8,
+    /// The sub-feed that delivered it.
+    feed: String,
+    published_at: DateTime<Utc>,
+    /// The source's own update marker, e.g. Bloomberg's `updated_at`.
+    updated_at: Option<Value>,
+}
+
+impl Version {
+    fn of(article: &Article, feed: &str) -> Self {
+        let completeness = match article.body_status {
+            BodyStatus::Pending => 0,
+            BodyStatus::Missing => 1,
+            BodyStatus::Paywalled => 2,
+            BodyStatus::Truncated => 3,
+            BodyStatus::Full => 4,
+        };
+        Self {
+            completeness,
+            feed: feed.to_string(),
+            published_at: article.published_at,
+            updated_at: article.metadata.get(UPDATED_AT_KEY).cloned(),
+        }
+    }
+
+    /// A more complete body, e.g. the full story after its headline, or an update from
+    /// the source that delivered it: a later publication time or a changed update
+    /// marker. Other sources' copies of the story differ in wording, not in news.
+    fn supersedes(&self, delivered: &Version) -> bool {
+        if self.completeness != delivered.completeness {
+            return self.completeness > delivered.completeness;
+        }
+        self.feed == delivered.feed
+            && (self.published_at > delivered.published_at
+                || (self.updated_at.is_some() && self.updated_at != delivered.updated_at))
+    }
+}
+
+#[derive(Debug)]
+struct Seen {
+    urls: Recent<Version>,
+    /// Publication time of the story each normalized title was last delivered with.
+    titles: Recent<DateTime<Utc>>,
+}
+
+impl Seen {
+    fn new(capacity: usize) -> Self {
+        Self { urls: Recent::new(capacity), titles: Recent::new(capacity) }
+    }
+}
+
+/// Combines several feeds into one, dropping articles already delivered by any
+/// sub-feed and tagging each article with the name of the sub-feed it came from.
+/// An article is a duplicate if it has the same canonical URL as one delivered before,
+/// or the same normalized title as one published within the title window of it.
+/// A new version of a delivered URL is not a duplicate: a more complete body, such as
+/// the full story after a [`BodyStatus::Pending`] headline, or an update from the same
+/// sub-feed with a later publication time or a changed `updated_at` in its metadata.
+pub struct MultiFeed {
+    feeds: Vec<(String, Box<dyn Feed>)>,
+    seen: Mutex<Seen>,
+    title_window: Duration,
+}
+
+impl Default for MultiFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiFeed {
+    pub fn new() -> Self {
+        Self {
+            feeds: Vec::new(),
+            seen: Mutex::new(Seen::new(DEFAULT_MEMORY)),
+            title_window: Duration::hours(24),
+        }
+    }
+
+    pub fn with_feed<F: Feed + 'static>(mut self, name: &str, feed: F) -> Self {
+        self.add_feed(name, feed);
+        self
+    }
+
+    pub fn add_feed<F: Feed + 'static>(&mut self, name: &str, feed: F) {
+        self.feeds.push((name.to_string(), Box::new(feed)));
+    }
+
+    /// Number of URLs and titles remembered for deduplication across polls.
+    pub fn with_memory(self, capacity: usize) -> Self {
+        Self {
+            seen: Mutex::new(Seen::new(capacity.max(1))),
+            ..self
+        }
+    }
+
+    /// How far apart in publication time two articles with the same title can be to
+    /// count as the same story. Recurring headlines such as "Stocks Open Higher" outside
+    /// of it are different stories.
+    pub fn with_title_window(mut self, title_window: Duration) -> Self {
+        self.title_window = title_window;
+        self
+    }
+
+    pub fn feed_names(&self) -> impl Iterator<Item = &str> {
+        self.feeds.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Polls every sub-feed concurrently. A failing sub-feed is reported in
+    /// `failures` and does not affect the articles of the others.
+    pub async fn poll(&self) -> MultiFeedReport {
+        let polls: Vec<_> = self.feeds.iter().map(|(name, feed)| poll_feed(name, feed.as_ref())).collect();
+        let results = join_all(polls).await;
+
+        let mut report = MultiFeedReport::default();
+        let mut seen = self.seen.lock().unwrap();
+        for (name, result) in results {
+            let fetched = match result {
+                Ok(fetched) => fetched,
+                Err(error) => {
+                    report.failures.push(FeedFailure { feed: name.clone(), error });
+                    continue;
+                }
+            };
+            report.story_failures.extend(fetched.failures);
+
+            for mut article in fetched.articles {
+                let url_key = canonical_url(&article.url);
+                let title = normalized_title(&article.title);
+                let published_at = article.published_at;
+
+                let version = Version::of(&article, name);
+
+                let same_title = seen
+                    .titles
+                    .get(&title)
+                    .is_some_and(|other| (*other - published_at).abs() <= self.title_window);
+                let duplicate = match seen.urls.get(&url_key) {
+                    Some(delivered) => !version.supersedes(delivered),
+                    None => !title.is_empty() && same_title,
+                };
+                if duplicate {
+                    continue;
+                }
+                seen.urls.insert(url_key, version);
+                if !title.is_empty() {
+                    seen.titles.insert(title, published_at);
+                }
+
+                article.origin = Some(name.clone());
+                report.articles.push(article);
+            }
+        }
+
+        // Titles outside the window of the newest story can no longer match anything recent.
+        if let Some(latest) = report.articles.iter().map(|article| article.published_at).max() {
+            let window = self.title_window;
+            seen.titles.retain(|published_at| latest - *published_at <= window);
+        }
+
+        report.articles.sort_by_key(|article| article.published_at);
+        report
+    }
+}
+
+async fn poll_feed<'a>(
+    name: &'a String,
+    feed: &'a dyn Feed,
+) -> (&'a String, Result<FetchReport, Box<dyn Error + Send + Sync>>) {
+    (name, feed.fetch_report().await.map_err(sendable))
+}
+
+/// Makes the error `Send`, so the poll can be awaited together with the other feeds.
+/// A [`FeedError`] stays as it is; anything else keeps only its message.
+fn sendable(error: Box<dyn Error>) -> Box<dyn Error + Send + Sync> {
+    match error.downcast::<FeedError>() {
+        Ok(error) => error,
+        Err(error) => error.to_string().into(),
+    }
+}
+
+#[async_trait]
+impl Feed for MultiFeed {
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        Ok(self.fetch_report().await?.articles)
+    }
+
+    /// Fails with [`AllFeedsFailed`] only if every sub-feed failed.
+    async fn fetch_report(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        let report = self.poll().await;
+        if !self.feeds.is_empty() && report.failures.len() == self.feeds.len() {
+            return Err(Box::new(AllFeedsFailed(report.failures)));
+        }
+        Ok(FetchReport { articles: report.articles, failures: report.story_failures })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the next batch on every poll.
+    struct Batches(Mutex<VecDeque<Vec<Article>>>);
+
+    impl Batches {
+        fn new(batches: Vec<Vec<Article>>) -> Self {
+            Self(Mutex::new(batches.into()))
+        }
+    }
+
+    #[async_trait]
+    impl Feed for Batches {
+        async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+            Ok(self.0.lock().unwrap().pop_front().unwrap_or_default())
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait]
+    impl Feed for Failing {
+        async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+            Err(FeedError::Paywalled { url: "https://example.com/story".to_string() }.into())
+        }
+    }
+
+    fn article(url: &str, title: &str, published_at: &str) -> Article {
+        Article {
+            title: title.to_string(),
+            url: url.to_string(),
+            published_at: published_at.parse().unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn same_title_is_a_duplicate_only_within_the_window() {
+        let first = Batches::new(vec![
+            vec![article("https://a.example/open-1", "Stocks Open Higher", "2024-12-02T14:30:00Z")],
+            vec![article("https://a.example/open-2", "Stocks Open Higher", "2024-12-05T14:30:00Z")],
+        ]);
+        let second = Batches::new(vec![vec![article(
+            "https://b.example/markets/open",
+            "Stocks open higher.",
+            "2024-12-02T14:32:00Z",
+        )]]);
+        let feed = MultiFeed::new().with_feed("a", first).with_feed("b", second);
+
+        let report = feed.poll().await;
+        assert_eq!(report.articles.len(), 1);
+        assert_eq!(report.articles[0].origin.as_deref(), Some("a"));
+
+        let report = feed.poll().await;
+        assert_eq!(report.articles.len(), 1);
+        assert_eq!(report.articles[0].url, "https://a.example/open-2");
+    }
+
+    #[tokio::test]
+    async fn full_story_follows_its_headline() {
+        let url = "https://www.bloomberg.com/news/articles/2024-12-05/bitcoin-tops-100-000";
+        let headline = Article {
+            body_status: BodyStatus::Pending,
+            ..article(url, "Bitcoin Tops $100,000", "2024-12-05T02:42:11Z")
+        };
+        let story = Article {
+            body: "Bitcoin rose above $100,000 for the first time.".to_string(),
+            body_status: BodyStatus::Full,
+            ..headline.clone()
+        };
+        let teaser = Article { body_status: BodyStatus::Truncated, body: "Bitcoin rose.".to_string(), ..story.clone() };
+        let feed = MultiFeed::new()
+            .with_feed("bloomberg", Batches::new(vec![vec![headline], vec![story.clone()], vec![story]]))
+            .with_feed("rss", Batches::new(vec![Vec::new(), Vec::new(), vec![teaser]]));
+
+        assert_eq!(feed.poll().await.articles[0].body_status, BodyStatus::Pending);
+        assert_eq!(feed.poll().await.articles[0].body_status, BodyStatus::Full);
+        // Neither a re-delivery nor a less complete version is new.
+        assert!(feed.poll().await.articles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn only_the_delivering_source_updates_a_story() {
+        let url = "https://www.example.com/news/fed-holds";
+        let story = Article { body: "The Fed held rates.".to_string(), ..article(url, "Fed Holds", "2024-12-18T19:00:00Z") };
+        let reworded = Article { body: "The Federal Reserve held rates steady.".to_string(), ..story.clone() };
+        let republished = Article { published_at: "2024-12-18T19:30:00Z".parse().unwrap(), ..reworded.clone() };
+        let mut marked = reworded.clone();
+        marked.metadata.insert(UPDATED_AT_KEY.to_string(), Value::from("2024-12-18T19:45:00Z"));
+
+        let feed = MultiFeed::new()
+            .with_feed("wire", Batches::new(vec![vec![story], Vec::new(), vec![reworded.clone()], vec![republished], vec![marked]]))
+            .with_feed("aggregator", Batches::new(vec![Vec::new(), vec![reworded]]));
+
+        assert_eq!(feed.poll().await.articles.len(), 1);
+        // Another source's copy with the same completeness is a duplicate.
+        assert!(feed.poll().await.articles.is_empty());
+        // So is a changed body without a newer time or marker from the same source.
+        assert!(feed.poll().await.articles.is_empty());
+        assert_eq!(feed.poll().await.articles[0].published_at, "2024-12-18T19:30:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(feed.poll().await.articles.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failures_keep_the_feed_error() {
+        let feed = MultiFeed::new().with_feed("ok", Batches::new(Vec::new())).with_feed("paywalled", Failing);
+        let report = feed.poll().await;
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].feed, "paywalled");
+        assert!(matches!(report.failures[0].error.downcast_ref::<FeedError>(), Some(FeedError::Paywalled { .. })));
+
+        let feed = MultiFeed::new().with_feed("paywalled", Failing);
+        let error = feed.get_new_articles().await.unwrap_err();
+        let failures = &error.downcast_ref::<AllFeedsFailed>().unwrap().0;
+        assert!(failures[0].error.is::<FeedError>());
+    }
+}
@@ -0,0 +1,303 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+use async_trait::async_trait;
+use super::base::{Article, Feed};
+use super::bloomberg::Bloomberg;
+use super::extract;
+
+/// How a [`ReplayFeed`] releases its articles.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayPace {
+    /// Every article on the first poll.
+    Immediate,
+    /// Articles become available as the time between their original `published_at`
+    /// timestamps elapses, divided by `speed` (2.0 replays twice as fast).
+    Original { speed: f64 },
+}
+
+#[derive(Debug, Default)]
+struct ReplayState {
+    cursor: usize,
+    started_at: Option<Instant>,
+}
+
+/// Feed that plays back previously saved articles, for demos, regression tests
+/// and backtesting without network access.
+pub struct ReplayFeed {
+    articles: Vec<Article>,
+    pace: ReplayPace,
+    state: Mutex<ReplayState>,
+}
+
+impl ReplayFeed {
+    pub fn from_articles(mut articles: Vec<Article>) -> Self {
+        articles.sort_by_key(|article| article.published_at);
+        Self {
+            articles,
+            pace: ReplayPace::Immediate,
+            state: Mutex::new(ReplayState::default()),
+        }
+    }
+
+    /// Reads one JSON-encoded article per line. Blank lines are ignored.
+    pub fn from_jsonl<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::from_articles(read_jsonl(path.as_ref())?))
+    }
+
+    /// Reads every `.jsonl` file, `.json` file (one article or an array of them) and
+    /// `.html` story snapshot in `dir`. A snapshot without a publication date is placed
+    /// at the time the file was last modified.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut paths: Vec<_> = fs::read_dir(dir.as_ref())?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file())
+            .collect();
+        paths.sort();
+
+        let mut articles = Vec::new();
+        for path in paths {
+            let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_lowercase();
+            match extension.as_str() {
+                "jsonl" => articles.extend(read_jsonl(&path)?),
+                "json" => articles.extend(read_json(&path)?),
+                "html" | "htm" => articles.push(read_html(&path)?),
+                _ => {}
+            }
+        }
+
+        Ok(Self::from_articles(articles))
+    }
+
+    pub fn with_pace(mut self, pace: ReplayPace) -> Self {
+        self.pace = pace;
+        self
+    }
+
+    /// Shorthand for [`ReplayPace::Original`].
+    pub fn paced(self, speed: f64) -> Self {
+        self.with_pace(ReplayPace::Original { speed })
+    }
+
+    pub fn len(&self) -> usize {
+        self.articles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.articles.is_empty()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.articles.len() - self.state.lock().unwrap().cursor
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Starts the replay over from the first article.
+    pub fn rewind(&self) {
+        *self.state.lock().unwrap() = ReplayState::default();
+    }
+
+    fn release(&self) -> Vec<Article> {
+        let mut state = self.state.lock().unwrap();
+        let started_at = *state.started_at.get_or_insert_with(Instant::now);
+
+        let end = match self.pace {
+            ReplayPace::Immediate => self.articles.len(),
+            ReplayPace::Original { speed } => {
+                let Some(first) = self.articles.first() else {
+                    return Vec::new();
+                };
+                let elapsed = started_at.elapsed().as_secs_f64() * speed.max(f64::MIN_POSITIVE);
+                let offset = self.articles[state.cursor..]
+                    .iter()
+                    .take_while(|article| {
+                        let since_first = (article.published_at - first.published_at).num_milliseconds() as f64 / 1000.0;
+                        since_first <= elapsed
+                    })
+                    .count();
+                state.cursor + offset
+            }
+        };
+
+        let released = self.articles[state.cursor..end].to_vec();
+        state.cursor = end;
+        released
+    }
+}
+
+fn read_jsonl(path: &Path) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+    fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e).into())
+        })
+        .collect()
+}
+
+fn read_json(path: &Path) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+    let value: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let articles = match value {
+        serde_json::Value::Array(_) => serde_json::from_value(value)?,
+        _ => vec![serde_json::from_value(value)?],
+    };
+    Ok(articles)
+}
+
+/// Bloomberg snapshots go through the Bloomberg story parser, anything else through
+/// the generic metadata extractor, which counts the file's modification time as the
+/// fetch time.
+fn read_html(path: &Path) -> Result<Article, Box<dyn std::error::Error>> {
+    let html = fs::read_to_string(path)?;
+    let extracted = extract::extract(&html);
+    let url = extracted.url.clone().unwrap_or_else(|| format!("file://{}", path.display()));
+
+    if is_bloomberg(&url) {
+        return Bloomberg::parse_story(&url, &html).map_err(|e| format!("{}: {}", path.display(), e).into());
+    }
+    if extracted.title.is_none() {
+        return Err(format!("{}: No article title found", path.display()).into());
+    }
+    let mut article = extracted.into_article(&url, "Replay");
+    article.fetched_at = Some(fs::metadata(path)?.modified()?.into());
+    if article.published_at_is_estimated() {
+        article.set_published_at(None);
+    }
+    Ok(article)
+}
+
+fn is_bloomberg(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_lowercase))
+        .is_some_and(|host| host == "bloomberg.com" || host.ends_with(".bloomberg.com"))
+}
+
+#[async_trait]
+impl Feed for ReplayFeed {
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        Ok(self.release())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+    use chrono::{DateTime, Utc};
+    use crate::feeds::test_util::TempDir;
+
+    const BLOOMBERG_STORY: &str = include_str!("../../tests/fixtures/bloomberg/story.html");
+
+    fn article(url: &str, published_at: &str) -> Article {
+        let mut article = Article { url: url.to_string(), title: url.to_string(), ..Default::default() };
+        article.set_published_at(Some(published_at.parse().unwrap()));
+        article
+    }
+
+    fn urls(articles: &[Article]) -> Vec<&str> {
+        articles.iter().map(|article| article.url.as_str()).collect()
+    }
+
+    #[test]
+    fn loads_jsonl_json_and_html_from_a_directory() {
+        let dir = TempDir::new();
+        let line = |url, published_at| serde_json::to_string(&article(url, published_at)).unwrap();
+        dir.write(
+            "a.jsonl",
+            &format!(
+                "{}\n\n{}\n",
+                line("https://example.com/3", "2025-01-06T12:03:00Z"),
+                line("https://example.com/1", "2025-01-06T12:01:00Z")
+            ),
+        );
+        dir.write("b.json", &format!("[{}]", line("https://example.com/2", "2025-01-06T12:02:00Z")));
+        dir.write("c.json", &line("https://example.com/4", "2025-01-06T12:04:00Z"));
+        dir.write("notes.txt", "not an article");
+        dir.write("story.html", BLOOMBERG_STORY);
+        let undated = dir.write(
+            "undated.html",
+            r#"<html><head><meta property="og:title" content="Undated"><meta property="og:url" content="https://example.com/?via=bloomberg.com"></head></html>"#,
+        );
+        let saved_at: DateTime<Utc> = "2025-01-06T12:00:30Z".parse().unwrap();
+        fs::File::options().write(true).open(&undated).unwrap().set_modified(SystemTime::from(saved_at)).unwrap();
+
+        let feed = ReplayFeed::from_dir(&dir.0).unwrap();
+        let articles = &feed.articles;
+        assert_eq!(
+            urls(articles),
+            [
+                "https://www.bloomberg.com/news/articles/2024-12-05/bitcoin-tops-100-000",
+                "https://example.com/?via=bloomberg.com",
+                "https://example.com/1",
+                "https://example.com/2",
+                "https://example.com/3",
+                "https://example.com/4",
+            ]
+        );
+        assert_eq!(articles[0].source, "Bloomberg");
+        assert_eq!(articles[1].source, "Replay");
+        assert_eq!(articles[1].published_at, saved_at);
+        assert!(articles[1].published_at_is_estimated());
+    }
+
+    #[test]
+    fn jsonl_errors_name_the_line() {
+        let dir = TempDir::new();
+        let line = serde_json::to_string(&article("https://example.com/1", "2025-01-06T12:01:00Z")).unwrap();
+        let path = dir.write("broken.jsonl", &format!("{}\n{{\"title\":\n", line));
+        let error = ReplayFeed::from_jsonl(&path).err().unwrap().to_string();
+        assert!(error.contains("broken.jsonl:2:"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn immediate_pace_releases_everything_once() {
+        let feed = ReplayFeed::from_articles(vec![
+            article("https://example.com/2", "2025-01-06T12:02:00Z"),
+            article("https://example.com/1", "2025-01-06T12:01:00Z"),
+        ]);
+        assert_eq!(urls(&feed.get_new_articles().await.unwrap()), ["https://example.com/1", "https://example.com/2"]);
+        assert!(feed.get_new_articles().await.unwrap().is_empty());
+        assert!(feed.is_finished());
+
+        feed.rewind();
+        assert_eq!(feed.remaining(), 2);
+        assert_eq!(feed.get_new_articles().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn original_pace_follows_the_publication_times() {
+        let feed = ReplayFeed::from_articles(vec![
+            article("https://example.com/0", "2025-01-06T12:00:00Z"),
+            article("https://example.com/1", "2025-01-06T12:00:01Z"),
+            article("https://example.com/60", "2025-01-06T12:01:00Z"),
+        ])
+        .paced(10.0);
+
+        assert_eq!(urls(&feed.get_new_articles().await.unwrap()), ["https://example.com/0"]);
+        // One second of the original at ten times the speed.
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(urls(&feed.get_new_articles().await.unwrap()), ["https://example.com/1"]);
+        assert!(feed.get_new_articles().await.unwrap().is_empty());
+        assert_eq!(feed.remaining(), 1);
+
+        feed.rewind();
+        assert_eq!(urls(&feed.get_new_articles().await.unwrap()), ["https://example.com/0"]);
+    }
+
+    #[test]
+    fn recognizes_bloomberg_by_host() {
+        assert!(is_bloomberg("https://www.bloomberg.com/news/articles/x"));
+        assert!(is_bloomberg("https://BLOOMBERG.com/x"));
+        assert!(!is_bloomberg("https://example.com/?via=bloomberg.com"));
+        assert!(!is_bloomberg("https://notbloomberg.com/x"));
+        assert!(!is_bloomberg("file:///tmp/bloomberg.com.html"));
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::Utc;
+use roxmltree::{Document, Node};
+use super::base::{article_id, Article};
+use super::base::BodyStatus;
+use super::base::Feed;
+use super::error::FeedError;
+use super::extract::{html_to_text, parse_date};
+use super::http::HttpClient;
+use super::xml::{child, namespaced_child, text_of};
+
+const DC_NS: &str = "http://purl.org/dc/elements/1.1/";
+const CONTENT_NS: &str = "http://purl.org/rss/1.0/modules/content/";
+/// Item URLs remembered so that every poll only returns items not returned before.
+const SEEN_ITEMS: usize = 10_000;
+
+/// Feed for any RSS 2.0, RSS 1.0 (RDF) or Atom document.
+pub struct RssFeed {
+    http: HttpClient,
+    url: String,
+    source: Option<String>,
+    seen: Mutex<(HashSet<String>, VecDeque<String>)>,
+}
+
+impl RssFeed {
+    pub fn new(url: &str) -> Self {
+        Self {
+            http: HttpClient::new(),
+            url: url.to_string(),
+            source: None,
+            seen: Mutex::new((HashSet::new(), VecDeque::new())),
+        }
+    }
+
+    /// Overrides the `source` of every article, which otherwise is the channel title.
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// Polls through `http`, so its conditional GETs and per-host pacing cover this feed too.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Parses an RSS or Atom document into articles.
+    pub fn parse(&self, xml: &str) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        let document = Document::parse(xml)?;
+        let root = document.root_element();
+
+        let source = match &self.source {
+            Some(source) => source.clone(),
+            None => channel_title(root).unwrap_or_else(|| self.url.clone()),
+        };
+
+        let language = channel_language(root);
+
+        let articles = root
+            .descendants()
+            .filter(|n| n.is_element() && matches!(n.tag_name().name(), "item" | "entry"))
+            .filter_map(|item| parse_item(item, &source, language.as_deref()))
+            .collect();
+
+        Ok(articles)
+    }
+}
+
+fn channel_title(root: Node) -> Option<String> {
+    // RSS keeps the title under <channel>, Atom directly under <feed>.
+    let channel = child(root, "channel").unwrap_or(root);
+    child(channel, "title").map(text_of).filter(|t| !t.is_empty())
+}
+
+fn channel_language(root: Node) -> Option<String> {
+    // Atom declares it with `xml:lang`, RSS with <language> or <dc:language>.
+    let channel = child(root, "channel").unwrap_or(root);
+    root.attribute(("http://www.w3.org/XML/1998/namespace", "lang"))
+        .map(str::to_string)
+        .or_else(|| child(channel, "language").map(text_of))
+        .or_else(|| namespaced_child(channel, DC_NS, "language").map(text_of))
+        .filter(|l| !l.is_empty())
+}
+
+fn parse_item(item: Node, source: &str, language: Option<&str>) -> Option<Article> {
+    let title = child(item, "title").map(text_of).unwrap_or_default();
+    let url = item_link(item)?;
+
+    let author = namespaced_child(item, DC_NS, "creator")
+        .map(text_of)
+        .or_else(|| child(item, "author").map(|a| child(a, "name").map(text_of).unwrap_or_else(|| text_of(a))))
+        .unwrap_or_default();
+
+    let published_at = ["pubDate", "published", "updated", "date"]
+        .iter()
+        .filter_map(|name| child(item, name))
+        .find_map(|n| parse_date(&text_of(n)));
+
+    let content = namespaced_child(item, CONTENT_NS, "encoded")
+        .or_else(|| child(item, "content"))
+        .map(|n| html_to_text(&text_of(n)))
+        .filter(|c| !c.is_empty());
+    let summary = child(item, "description")
+        .or_else(|| child(item, "summary"))
+        .map(|n| html_to_text(&text_of(n)))
+        .filter(|s| !s.is_empty());
+
+    // Without a full content element the description is all the feed gives us.
+    let (body, body_status) = match (content, &summary) {
+        (Some(content), _) => (content, BodyStatus::Full),
+        (None, Some(summary)) => (summary.clone(), BodyStatus::Truncated),
+        (None, None) => (String::new(), BodyStatus::Missing),
+    };
+
+    // RSS categories are text, Atom categories carry a `term`.
+    let tags = item
+        .children()
+        .filter(|n| n.is_element() && n.tag_name().name() == "category")
+        .map(|n| n.attribute("term").map(str::to_string).unwrap_or_else(|| text_of(n)))
+        .filter(|t| !t.is_empty())
+        .collect();
+    let language = namespaced_child(item, DC_NS, "language")
+        .map(text_of)
+        .filter(|l| !l.is_empty())
+        .or(language.map(str::to_string));
+
+    let mut article = Article {
+        id: article_id(&url),
+        title,
+        author,
+        body,
+        summary,
+        body_status,
+        url,
+        source: source.to_string(),
+        origin: None,
+        fetched_at: Some(Utc::now()),
+        language,
+        tags,
+        ..Default::default()
+    };
+    article.set_published_at(published_at);
+    Some(article)
+}
+
+fn item_link(item: Node) -> Option<String> {
+    // Atom links carry the URL in `href` and may list several relations.
+    let atom_link = item
+        .children()
+        .filter(|n| n.is_element() && n.tag_name().name() == "link")
+        .filter(|n| matches!(n.attribute("rel"), None | Some("alternate")))
+        .find_map(|n| n.attribute("href"));
+    if let Some(href) = atom_link {
+        return Some(href.trim().to_string());
+    }
+
+    child(item, "link")
+        .map(text_of)
+        .filter(|l| !l.is_empty())
+        .or_else(|| {
+            child(item, "guid")
+                .filter(|g| g.attribute("isPermaLink") != Some("false"))
+                .map(text_of)
+                .filter(|g| g.starts_with("http"))
+        })
+        .or_else(|| item.attribute(("http://www.w3.org/1999/02/22-rdf-syntax-ns#", "about")).map(str::to_string))
+}
+
+#[async_trait]
+impl Feed for RssFeed {
+    /// Items not returned before, in document order.
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        let request = self.http
+            .get(&self.url)
+            .header("accept", "application/rss+xml, application/atom+xml, application/xml;q=0.9, */*;q=0.8");
+        let response = self.http.fetch(request).await?;
+        let mut articles = self.parse(&response.body).map_err(|e| FeedError::parse(&self.url, e))?;
+
+        let mut seen = self.seen.lock().unwrap();
+        let (urls, order) = &mut *seen;
+        articles.retain(|article| {
+            let new = urls.insert(article.url.clone());
+            if new {
+                order.push_back(article.url.clone());
+            }
+            new
+        });
+        while order.len() > SEEN_ITEMS {
+            if let Some(oldest) = order.pop_front() {
+                urls.remove(&oldest);
+            }
+        }
+        Ok(articles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::feeds::test_util::{respond, serve};
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <channel>
+    <title>Markets Wire</title>
+    <language>en-us</language>
+    <item>
+      <title>Fed Holds Rates Steady</title>
+      <link>https://example.com/fed</link>
+      <dc:creator>Jane Doe</dc:creator>
+      <pubDate>Wed, 18 Dec 2024 19:00:00 GMT</pubDate>
+      <category>Economy</category>
+      <description><![CDATA[<p>The Fed <b>held</b> rates.</p>]]></description>
+      <content:encoded><![CDATA[<p>The Federal Reserve held rates steady on Wednesday.</p><p>Officials signaled fewer cuts.</p>]]></content:encoded>
+    </item>
+    <item>
+      <title>Oil Slips</title>
+      <guid isPermaLink="true">https://example.com/oil</guid>
+      <description>Crude fell 2%.</description>
+    </item>
+    <item>
+      <title>No link at all</title>
+      <guid isPermaLink="false">tag:example.com,2024:3</guid>
+    </item>
+  </channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xml:lang="de">
+  <title>Boersen Blog</title>
+  <entry>
+    <title>DAX auf Rekordhoch</title>
+    <link rel="edit" href="https://example.de/edit/1"/>
+    <link rel="alternate" href="https://example.de/dax"/>
+    <author><name>Max Muster</name></author>
+    <published>2024-12-05T08:30:00+01:00</published>
+    <updated>2024-12-05T09:00:00+01:00</updated>
+    <category term="Aktien"/>
+    <summary>Der DAX steigt.</summary>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn parses_rss() {
+        let articles = RssFeed::new("https://example.com/rss").parse(RSS).unwrap();
+        assert_eq!(articles.len(), 2);
+
+        let fed = &articles[0];
+        assert_eq!(fed.title, "Fed Holds Rates Steady");
+        assert_eq!(fed.url, "https://example.com/fed");
+        assert_eq!(fed.id, article_id("https://example.com/fed"));
+        assert_eq!(fed.author, "Jane Doe");
+        assert_eq!(fed.source, "Markets Wire");
+        assert_eq!(fed.language.as_deref(), Some("en-us"));
+        assert_eq!(fed.tags, ["Economy"]);
+        assert_eq!(fed.published_at.to_rfc3339(), "2024-12-18T19:00:00+00:00");
+        assert!(!fed.published_at_is_estimated());
+        assert_eq!(fed.body, "The Federal Reserve held rates steady on Wednesday. Officials signaled fewer cuts.");
+        assert_eq!(fed.body_status, BodyStatus::Full);
+        assert_eq!(fed.summary.as_deref(), Some("The Fed held rates."));
+
+        // A permalink guid stands in for the link; the description is all there is.
+        let oil = &articles[1];
+        assert_eq!(oil.url, "https://example.com/oil");
+        assert_eq!(oil.body, "Crude fell 2%.");
+        assert_eq!(oil.body_status, BodyStatus::Truncated);
+        assert!(oil.published_at_is_estimated());
+        assert_eq!(Some(oil.published_at), oil.fetched_at);
+    }
+
+    #[test]
+    fn parses_atom() {
+        let articles = RssFeed::new("https://example.de/atom").with_source("Blog").parse(ATOM).unwrap();
+        assert_eq!(articles.len(), 1);
+
+        let dax = &articles[0];
+        assert_eq!(dax.title, "DAX auf Rekordhoch");
+        assert_eq!(dax.url, "https://example.de/dax");
+        assert_eq!(dax.author, "Max Muster");
+        assert_eq!(dax.source, "Blog");
+        assert_eq!(dax.language.as_deref(), Some("de"));
+        assert_eq!(dax.tags, ["Aktien"]);
+        assert_eq!(dax.published_at.to_rfc3339(), "2024-12-05T07:30:00+00:00");
+        assert_eq!(dax.body_status, BodyStatus::Truncated);
+    }
+
+    #[tokio::test]
+    async fn returns_each_item_once() {
+        let document = Arc::new(Mutex::new(RSS.to_string()));
+        let served = document.clone();
+        let base_url = serve(move |request| {
+            let document = served.lock().unwrap().clone();
+            let etag = format!("\"{}\"", document.len());
+            if request.headers().get("if-none-match").is_some_and(|value| value == etag.as_str()) {
+                return respond(304, &[("etag", &etag)], "");
+            }
+            respond(200, &[("etag", &etag)], &document)
+        })
+        .await;
+        let feed = RssFeed::new(&format!("{}/rss", base_url));
+
+        assert_eq!(feed.get_new_articles().await.unwrap().len(), 2);
+        // Unchanged, so answered with a 304 and parsed from the cached body again.
+        assert!(feed.get_new_articles().await.unwrap().is_empty());
+        assert_eq!(feed.http.stats().revalidated, 1);
+
+        let item = "<item><title>Gold Rallies</title><link>https://example.com/gold</link></item>";
+        *document.lock().unwrap() = RSS.replacen("<item>", &format!("{}<item>", item), 1);
+        let articles = feed.get_new_articles().await.unwrap();
+        assert_eq!(articles.len(), 1);
+        assert_eq!(articles[0].url, "https://example.com/gold");
+    }
+
+    #[test]
+    fn rejects_malformed_xml() {
+        assert!(RssFeed::new("https://example.com/rss").parse("<rss><channel>").is_err());
+    }
+}
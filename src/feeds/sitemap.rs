@@ -0,0 +1,424 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use roxmltree::{Document, Node};
+use super::base::{article_id, Article, BodyStatus, Feed, FetchReport, StoryError};
+use super::error::FeedError;
+use super::extract::{self, parse_date};
+use super::http::HttpClient;
+use super::xml::{child, child_text, namespaced_child, text_of};
+
+const NEWS_NS: &str = "http://www.google.com/schemas/sitemap-news/0.9";
+
+/// A `<url>` of a Google News sitemap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapEntry {
+    pub url: String,
+    pub title: Option<String>,
+    pub publication: Option<String>,
+    pub language: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub keywords: Vec<String>,
+}
+
+/// Parsed sitemap document: either an index of other sitemaps or a list of pages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sitemap {
+    Index(Vec<String>),
+    Urls(Vec<SitemapEntry>),
+}
+
+/// Feed over a publisher's Google News sitemap (`news:news` entries), following
+/// sitemap indexes one level deep.
+pub struct NewsSitemapFeed {
+    http: HttpClient,
+    url: String,
+    source: Option<String>,
+    window: Option<chrono::Duration>,
+    keywords: Vec<String>,
+    fetch_pages: bool,
+    max_sitemaps: usize,
+    concurrency: usize,
+    request_timeout: Duration,
+    seen: Mutex<HashSet<String>>,
+}
+
+impl NewsSitemapFeed {
+    /// `url` may point at a news sitemap or at a sitemap index.
+    pub fn new(url: &str) -> Self {
+        Self {
+            http: HttpClient::new(),
+            url: url.to_string(),
+            source: None,
+            window: Some(chrono::Duration::days(2)),
+            keywords: Vec::new(),
+            fetch_pages: false,
+            max_sitemaps: 10,
+            concurrency: 8,
+            request_timeout: Duration::from_secs(10),
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Overrides the `source` of every article, which otherwise is the sitemap's publication name.
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// Fetches sitemaps and pages with `http`, e.g. the client of an [`RssFeed`](super::rss::RssFeed)
+    /// for the same site, so both respect one rate limit.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Only entries published within `window` of now are returned; `None` keeps every entry.
+    pub fn with_window(mut self, window: Option<chrono::Duration>) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// Only entries whose title or `news:keywords` contain one of `keywords`
+    /// (case-insensitive) are returned. Empty keeps every entry.
+    pub fn with_keywords(mut self, keywords: &[&str]) -> Self {
+        self.keywords = keywords.iter().map(|k| k.to_lowercase()).collect();
+        self
+    }
+
+    /// Fetches every new page and fills the body through the metadata extractor.
+    pub fn with_page_fetching(mut self, fetch_pages: bool) -> Self {
+        self.fetch_pages = fetch_pages;
+        self
+    }
+
+    /// Maximum number of child sitemaps read from an index per poll.
+    pub fn with_max_sitemaps(mut self, max_sitemaps: usize) -> Self {
+        self.max_sitemaps = max_sitemaps.max(1);
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    pub fn parse(xml: &str) -> Result<Sitemap, Box<dyn std::error::Error>> {
+        let document = Document::parse(xml)?;
+        let root = document.root_element();
+
+        if root.tag_name().name() == "sitemapindex" {
+            let locations = root
+                .children()
+                .filter(|n| n.is_element() && n.tag_name().name() == "sitemap")
+                .filter_map(|sitemap| child_text(sitemap, "loc"))
+                .collect();
+            return Ok(Sitemap::Index(locations));
+        }
+
+        let entries = root
+            .children()
+            .filter(|n| n.is_element() && n.tag_name().name() == "url")
+            .filter_map(|url| {
+                let loc = child_text(url, "loc")?;
+                let news = namespaced_child(url, NEWS_NS, "news");
+                let publication = news.and_then(|news| child(news, "publication"));
+
+                Some(SitemapEntry {
+                    url: loc,
+                    title: news.and_then(|news| news_text(news, "title")),
+                    publication: publication.and_then(|p| news_text(p, "name")),
+                    language: publication.and_then(|p| news_text(p, "language")),
+                    published_at: news
+                        .and_then(|news| news_text(news, "publication_date"))
+                        .or_else(|| child_text(url, "lastmod"))
+                        .and_then(|date| parse_date(&date)),
+                    keywords: news
+                        .and_then(|news| news_text(news, "keywords"))
+                        .map(|keywords| {
+                            keywords
+                                .split(',')
+                                .map(|k| k.trim().to_string())
+                                .filter(|k| !k.is_empty())
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                })
+            })
+            .collect();
+        Ok(Sitemap::Urls(entries))
+    }
+
+    /// Entries from the sitemap (and its children, for an index) that pass the window
+    /// and keyword filters and were not returned before, oldest first. Child sitemaps
+    /// that could not be read are returned as failures; only if every child fails does
+    /// the call fail.
+    pub async fn get_new_entries(&self) -> Result<(Vec<SitemapEntry>, Vec<StoryError>), Box<dyn std::error::Error>> {
+        let xml = self.get(&self.url).await?;
+        let sitemap = Self::parse(&xml).map_err(|e| FeedError::parse(&self.url, e))?;
+        let mut failures = Vec::new();
+        let mut entries = match sitemap {
+            Sitemap::Urls(entries) => entries,
+            Sitemap::Index(locations) => {
+                let locations = &locations[..locations.len().min(self.max_sitemaps)];
+                let mut entries = Vec::new();
+                for location in locations {
+                    let listed = match self.get(location).await {
+                        Ok(xml) => match Self::parse(&xml) {
+                            Ok(Sitemap::Urls(child_entries)) => Ok(child_entries),
+                            Ok(Sitemap::Index(_)) => Err(FeedError::parse(location, "nested sitemap index")),
+                            Err(e) => Err(FeedError::parse(location, e)),
+                        },
+                        Err(e) => Err(e),
+                    };
+                    match listed {
+                        Ok(child_entries) => entries.extend(child_entries),
+                        Err(e) => failures.push(StoryError::new(location, e)),
+                    }
+                }
+                if !locations.is_empty() && failures.len() == locations.len() {
+                    return Err(Box::new(failures.swap_remove(0).error));
+                }
+                entries
+            }
+        };
+
+        let cutoff = self.window.map(|window| Utc::now() - window);
+        let seen = self.seen.lock().unwrap();
+        let mut urls = HashSet::new();
+        entries.retain(|entry| {
+            let in_window = match (cutoff, entry.published_at) {
+                (Some(cutoff), Some(published_at)) => published_at >= cutoff,
+                _ => true,
+            };
+            in_window && self.matches_keywords(entry) && !seen.contains(&entry.url) && urls.insert(entry.url.clone())
+        });
+        entries.sort_by_key(|entry| entry.published_at);
+        Ok((entries, failures))
+    }
+
+    pub async fn fetch_new_articles(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        let (entries, failures) = self.get_new_entries().await?;
+
+        let results: Vec<Result<Article, StoryError>> = stream::iter(entries)
+            .map(|entry| self.entry_article(entry))
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+
+        let mut report = FetchReport { failures, ..Default::default() };
+        let mut seen = self.seen.lock().unwrap();
+        for result in results {
+            match result {
+                Ok(article) => {
+                    seen.insert(article.url.clone());
+                    report.articles.push(article);
+                }
+                Err(failure) => report.failures.push(failure),
+            }
+        }
+        Ok(report)
+    }
+
+    fn matches_keywords(&self, entry: &SitemapEntry) -> bool {
+        if self.keywords.is_empty() {
+            return true;
+        }
+        let title = entry.title.as_deref().unwrap_or_default().to_lowercase();
+        let keywords: Vec<String> = entry.keywords.iter().map(|k| k.to_lowercase()).collect();
+        self.keywords
+            .iter()
+            .any(|wanted| title.contains(wanted) || keywords.iter().any(|k| k.contains(wanted)))
+    }
+
+    async fn entry_article(&self, entry: SitemapEntry) -> Result<Article, StoryError> {
+        let source = self.source.clone().or(entry.publication.clone()).unwrap_or_else(|| {
+            reqwest::Url::parse(&entry.url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+                .unwrap_or_default()
+        });
+
+        let mut article = if self.fetch_pages {
+            let timeout = self.request_timeout;
+            let html = tokio::time::timeout(timeout, self.get(&entry.url))
+                .await
+                .unwrap_or_else(|_| Err(FeedError::TimedOut { url: entry.url.clone(), after: timeout }))
+                .map_err(|e| StoryError::new(&entry.url, e))?;
+            extract::extract(&html).into_article(&entry.url, &source)
+        } else {
+            Article {
+                body_status: BodyStatus::Missing,
+                fetched_at: Some(Utc::now()),
+                ..Default::default()
+            }
+        };
+
+        // The sitemap is authoritative for what it lists; the page only adds the text.
+        article.id = article_id(&entry.url);
+        article.url = entry.url;
+        article.source = source;
+        if entry.language.is_some() {
+            article.language = entry.language;
+        }
+        if !entry.keywords.is_empty() {
+            article.tags = entry.keywords;
+        }
+        if let Some(title) = entry.title {
+            article.title = title;
+        }
+        // Without a date in the sitemap, the page's date stands, if there is a page.
+        if entry.published_at.is_some() || !self.fetch_pages {
+            article.set_published_at(entry.published_at);
+        }
+        Ok(article)
+    }
+
+    async fn get(&self, url: &str) -> Result<String, FeedError> {
+        let request = self.http
+            .get(url)
+            .header("accept", "application/xml, text/xml;q=0.9, */*;q=0.8");
+        Ok(self.http.fetch(request).await?.body)
+    }
+}
+
+/// Text of a `news:` element, unless it is empty.
+fn news_text(node: Node, name: &str) -> Option<String> {
+    namespaced_child(node, NEWS_NS, name).map(text_of).filter(|text| !text.is_empty())
+}
+
+#[async_trait]
+impl Feed for NewsSitemapFeed {
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        Ok(self.fetch_new_articles().await?.articles)
+    }
+
+    async fn fetch_report(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        self.fetch_new_articles().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::test_util::{respond, serve};
+
+    fn news_url(loc: &str, title: &str, keywords: &str, published_at: Option<DateTime<Utc>>) -> String {
+        let date = published_at
+            .map(|date| format!("<news:publication_date>{}</news:publication_date>", date.to_rfc3339()))
+            .unwrap_or_default();
+        format!(
+            "<url><loc>{}</loc><news:news><news:publication><news:name>Example News</news:name>\
+             <news:language>en</news:language></news:publication>{}<news:title>{}</news:title>\
+             <news:keywords>{}</news:keywords></news:news></url>",
+            loc, date, title, keywords
+        )
+    }
+
+    fn sitemap(urls: &[String]) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" xmlns:news="{}">{}</urlset>"#,
+            NEWS_NS,
+            urls.concat()
+        )
+    }
+
+    /// Serves `xml` for every request and returns the sitemap URL.
+    async fn serve_sitemap(xml: String) -> String {
+        let base_url = serve(move |_| respond(200, &[("content-type", "application/xml")], &xml)).await;
+        format!("{}/news-sitemap.xml", base_url)
+    }
+
+    #[test]
+    fn parses_news_entries() {
+        let published_at = "2024-12-18T19:00:00Z".parse().unwrap();
+        let xml = sitemap(&[news_url("https://example.com/fed", "Fed Holds Rates", "rates, Fed ,", Some(published_at))]);
+        let Sitemap::Urls(entries) = NewsSitemapFeed::parse(&xml).unwrap() else {
+            panic!("not a urlset");
+        };
+        assert_eq!(
+            entries,
+            [SitemapEntry {
+                url: "https://example.com/fed".to_string(),
+                title: Some("Fed Holds Rates".to_string()),
+                publication: Some("Example News".to_string()),
+                language: Some("en".to_string()),
+                published_at: Some(published_at),
+                keywords: vec!["rates".to_string(), "Fed".to_string()],
+            }]
+        );
+
+        let index = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+            <sitemap><loc>https://example.com/news-1.xml</loc></sitemap></sitemapindex>"#;
+        assert_eq!(NewsSitemapFeed::parse(index).unwrap(), Sitemap::Index(vec!["https://example.com/news-1.xml".to_string()]));
+    }
+
+    #[tokio::test]
+    async fn filters_by_window_and_keywords() {
+        let now = Utc::now();
+        let url = serve_sitemap(sitemap(&[
+            news_url("https://example.com/old-fed", "Fed Minutes", "rates", Some(now - chrono::Duration::days(3))),
+            news_url("https://example.com/oil", "Oil Slips", "energy", Some(now - chrono::Duration::hours(2))),
+            news_url("https://example.com/fed", "Fed Holds", "rates", Some(now - chrono::Duration::hours(1))),
+            news_url("https://example.com/ecb", "ECB Cuts", "Rates, euro", None),
+        ]))
+        .await;
+
+        let urls = |(entries, _): (Vec<SitemapEntry>, _)| entries.into_iter().map(|entry| entry.url).collect::<Vec<_>>();
+
+        // Undated entries can't be placed outside the window, so they stay; oldest first.
+        let feed = NewsSitemapFeed::new(&url);
+        assert_eq!(
+            urls(feed.get_new_entries().await.unwrap()),
+            ["https://example.com/ecb", "https://example.com/oil", "https://example.com/fed"]
+        );
+
+        let feed = NewsSitemapFeed::new(&url).with_window(None).with_keywords(&["RATES"]);
+        assert_eq!(
+            urls(feed.get_new_entries().await.unwrap()),
+            ["https://example.com/ecb", "https://example.com/old-fed", "https://example.com/fed"]
+        );
+
+        // Titles match too, and returned entries are not returned again.
+        let feed = NewsSitemapFeed::new(&url).with_keywords(&["oil"]);
+        let report = feed.fetch_new_articles().await.unwrap();
+        assert_eq!(report.articles.len(), 1);
+        assert_eq!(report.articles[0].title, "Oil Slips");
+        assert_eq!(report.articles[0].source, "Example News");
+        assert!(feed.get_new_entries().await.unwrap().0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn broken_child_sitemap_is_reported() {
+        let child = sitemap(&[news_url("https://example.com/fed", "Fed Holds", "rates", Some(Utc::now()))]);
+        let base_url = serve(move |request| match request.uri().path() {
+            "/index.xml" => {
+                let host = request.headers()["host"].to_str().unwrap();
+                let sitemaps: String = ["news-1", "missing", "broken"]
+                    .iter()
+                    .map(|name| format!("<sitemap><loc>http://{}/{}.xml</loc></sitemap>", host, name))
+                    .collect();
+                respond(200, &[], &format!("<sitemapindex>{}</sitemapindex>", sitemaps))
+            }
+            "/news-1.xml" => respond(200, &[], &child),
+            "/broken.xml" => respond(200, &[], "<urlset><url>"),
+            _ => respond(404, &[], ""),
+        })
+        .await;
+
+        let report = NewsSitemapFeed::new(&format!("{}/index.xml", base_url)).fetch_new_articles().await.unwrap();
+        assert_eq!(report.articles.len(), 1);
+        assert_eq!(report.articles[0].url, "https://example.com/fed");
+        let mut failed: Vec<&str> = report.failures.iter().map(|failure| failure.url.as_str()).collect();
+        failed.sort_unstable();
+        assert_eq!(failed, [format!("{}/broken.xml", base_url), format!("{}/missing.xml", base_url)]);
+    }
+}
@@ -0,0 +1,815 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_client::rpc_request::{RpcError, RpcRequest};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use super::base::{article_id, Article, BodyStatus, Feed, FetchReport, StoryError};
+use super::error::FeedError;
+
+const DEFAULT_RPC_URL: &str = "https://api.mainnet-beta.solana.com";
+const EXPLORER_URL: &str = "https://explorer.solana.com";
+/// Timeout of each RPC request made by a client from [`SolanaFeed::new`].
+const RPC_TIMEOUT: Duration = Duration::from_secs(30);
+/// Most signatures `getSignaturesForAddress` returns per request.
+const SIGNATURE_PAGE: usize = 1000;
+/// Signatures remembered to skip transactions already reported under another address.
+const SEEN_SIGNATURES: usize = 10_000;
+/// Log lines kept for a failed transaction, counted from the end.
+const ERROR_LOG_LINES: usize = 20;
+
+/// Something notable in a transaction. Amounts are in SOL or whole tokens.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OnChainEvent {
+    SolTransfer { from: Option<String>, to: Option<String>, amount: f64 },
+    /// `from` and `to` are the owners of the token accounts.
+    TokenTransfer { mint: String, symbol: Option<String>, from: Option<String>, to: Option<String>, amount: f64 },
+    Mint { mint: String, symbol: Option<String>, amount: f64 },
+    Burn { mint: String, symbol: Option<String>, amount: f64 },
+    /// A failed transaction that invoked a watched program.
+    ProgramError { program: String, error: Value, logs: Vec<String> },
+}
+
+impl OnChainEvent {
+    fn headline(&self) -> String {
+        match self {
+            OnChainEvent::SolTransfer { from, to, amount } => {
+                format!("Large SOL transfer: {:.2} SOL from {} to {}", amount, short(from), short(to))
+            }
+            OnChainEvent::TokenTransfer { mint, symbol, from, to, amount } => format!(
+                "Large {} transfer: {:.2} from {} to {}",
+                symbol.as_deref().unwrap_or(mint),
+                amount,
+                short(from),
+                short(to)
+            ),
+            OnChainEvent::Mint { mint, symbol, amount } => {
+                format!("{} minted: {:.2}", symbol.as_deref().unwrap_or(mint), amount)
+            }
+            OnChainEvent::Burn { mint, symbol, amount } => {
+                format!("{} burned: {:.2}", symbol.as_deref().unwrap_or(mint), amount)
+            }
+            OnChainEvent::ProgramError { program, error, .. } => {
+                format!("Failed transaction in program {}: {}", program, error)
+            }
+        }
+    }
+
+    fn tag(&self) -> &'static str {
+        match self {
+            OnChainEvent::SolTransfer { .. } => "sol_transfer",
+            OnChainEvent::TokenTransfer { .. } => "token_transfer",
+            OnChainEvent::Mint { .. } => "mint",
+            OnChainEvent::Burn { .. } => "burn",
+            OnChainEvent::ProgramError { .. } => "program_error",
+        }
+    }
+
+    fn ticker(&self) -> Option<&str> {
+        match self {
+            OnChainEvent::SolTransfer { .. } => Some("SOL"),
+            OnChainEvent::TokenTransfer { symbol, .. }
+            | OnChainEvent::Mint { symbol, .. }
+            | OnChainEvent::Burn { symbol, .. } => symbol.as_deref(),
+            OnChainEvent::ProgramError { .. } => None,
+        }
+    }
+}
+
+fn short(address: &Option<String>) -> String {
+    match address {
+        Some(address) if address.len() > 10 => format!("{}…{}", &address[..4], &address[address.len() - 4..]),
+        Some(address) => address.clone(),
+        None => "unknown".to_string(),
+    }
+}
+
+/// A token whose large transfers, mints and burns are reported.
+#[derive(Debug, Clone)]
+struct TrackedToken {
+    symbol: String,
+    min_amount: f64,
+}
+
+/// Feed of notable on-chain activity, polled over RPC: large SOL and token transfers,
+/// mints and burns of tracked tokens, and failed transactions of watched programs.
+/// Every transaction touching a watched account or program is inspected; each one with
+/// something notable becomes an article whose metadata holds the [`OnChainEvent`]s.
+///
+/// Point it at `solana-test-validator` with `SolanaFeed::new("http://127.0.0.1:8899")`.
+pub struct SolanaFeed {
+    client: Arc<RpcClient>,
+    accounts: Vec<String>,
+    programs: Vec<String>,
+    tokens: HashMap<String, TrackedToken>,
+    min_sol_transfer: f64,
+    signature_limit: usize,
+    concurrency: usize,
+    /// Newest signature processed per watched address.
+    cursors: Mutex<HashMap<String, String>>,
+    seen: Mutex<(HashSet<String>, VecDeque<String>)>,
+}
+
+impl Default for SolanaFeed {
+    fn default() -> Self {
+        Self::new(DEFAULT_RPC_URL)
+    }
+}
+
+impl SolanaFeed {
+    pub fn new(rpc_url: &str) -> Self {
+        let client =
+            RpcClient::new_with_timeout_and_commitment(rpc_url.to_string(), RPC_TIMEOUT, CommitmentConfig::confirmed());
+        Self::with_client(Arc::new(client))
+    }
+
+    pub fn with_client(client: Arc<RpcClient>) -> Self {
+        Self {
+            client,
+            accounts: Vec::new(),
+            programs: Vec::new(),
+            tokens: HashMap::new(),
+            min_sol_transfer: 10_000.0,
+            signature_limit: 25,
+            concurrency: 4,
+            cursors: Mutex::new(HashMap::new()),
+            seen: Mutex::new((HashSet::new(), VecDeque::new())),
+        }
+    }
+
+    /// Watches the transactions of an account, e.g. an exchange hot wallet or a mint.
+    pub fn with_account(mut self, address: &str) -> Self {
+        self.accounts.push(address.to_string());
+        self
+    }
+
+    /// Watches the transactions of a program and reports the ones that fail.
+    pub fn with_program(mut self, program_id: &str) -> Self {
+        self.programs.push(program_id.to_string());
+        self
+    }
+
+    /// Reports transfers, mints and burns of `mint` of at least `min_amount` whole tokens.
+    pub fn with_token(mut self, symbol: &str, mint: &str, min_amount: f64) -> Self {
+        self.tokens.insert(mint.to_string(), TrackedToken { symbol: symbol.to_string(), min_amount });
+        self
+    }
+
+    /// Smallest SOL balance change reported as a transfer. 10,000 SOL by default.
+    pub fn with_min_sol_transfer(mut self, sol: f64) -> Self {
+        self.min_sol_transfer = sol;
+        self
+    }
+
+    /// Most recent transactions inspected per address on the first poll. Later polls
+    /// inspect every transaction since the previous one.
+    pub fn with_signature_limit(mut self, limit: usize) -> Self {
+        self.signature_limit = limit.clamp(1, SIGNATURE_PAGE);
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn client(&self) -> &RpcClient {
+        &self.client
+    }
+
+    /// Inspects the new transactions of every watched address. Addresses whose
+    /// transactions could not be listed are reported with their explorer URL, and
+    /// transactions that could not be fetched with theirs; both are tried again on the
+    /// next poll. Fails only if no address could be listed.
+    pub async fn fetch_new_articles(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        let mut report = FetchReport::default();
+        let mut listed = Vec::new();
+        for address in self.accounts.iter().chain(&self.programs) {
+            match self.new_signatures(address).await {
+                Ok(signatures) => listed.push((address, signatures)),
+                Err(e) => report.failures.push(StoryError::new(&address_url(address), e)),
+            }
+        }
+        if listed.is_empty() && !report.failures.is_empty() {
+            return Err(Box::new(report.failures.swap_remove(0).error));
+        }
+
+        let mut signatures: Vec<String> = Vec::new();
+        {
+            let seen = self.seen.lock().unwrap();
+            for signature in listed.iter().flat_map(|(_, signatures)| signatures) {
+                if !seen.0.contains(signature) && !signatures.contains(signature) {
+                    signatures.push(signature.clone());
+                }
+            }
+        }
+
+        let results: Vec<(String, Result<Option<Article>, StoryError>)> = stream::iter(signatures)
+            .map(|signature| async move { (signature.clone(), self.inspect(signature).await) })
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+
+        let mut seen = self.seen.lock().unwrap();
+        for (signature, result) in results {
+            match result {
+                Ok(article) => {
+                    if seen.0.insert(signature.clone()) {
+                        seen.1.push_back(signature);
+                    }
+                    report.articles.extend(article);
+                }
+                Err(failure) => report.failures.push(failure),
+            }
+        }
+
+        // Each cursor stops before the oldest transaction that failed, so it is listed
+        // again next time; the ones after it are skipped as seen. The first poll only
+        // samples the latest transactions, so there the cursor may skip a failure
+        // rather than stay unset and have the next poll start over.
+        let mut cursors = self.cursors.lock().unwrap();
+        for (address, signatures) in &listed {
+            let first = !cursors.contains_key(*address);
+            let newest = signatures
+                .iter()
+                .take_while(|s| seen.0.contains(*s))
+                .last()
+                .or_else(|| signatures.last().filter(|_| first));
+            if let Some(newest) = newest {
+                cursors.insert(address.to_string(), newest.clone());
+            }
+        }
+        while seen.1.len() > SEEN_SIGNATURES {
+            if let Some(oldest) = seen.1.pop_front() {
+                seen.0.remove(&oldest);
+            }
+        }
+
+        report.articles.sort_by_key(|article| article.published_at);
+        Ok(report)
+    }
+
+    /// Signatures of `address` since the last poll, oldest first. Until the address has
+    /// a cursor only the latest `signature_limit` are listed; after that, pages are
+    /// requested with `before` until the cursor is reached.
+    async fn new_signatures(&self, address: &str) -> Result<Vec<String>, FeedError> {
+        let url = address_url(address);
+        let pubkey = Pubkey::from_str(address).map_err(|e| FeedError::parse(&url, e))?;
+        let cursor = self.cursors.lock().unwrap().get(address).cloned();
+        let first = cursor.is_none();
+        let until = cursor.and_then(|s| Signature::from_str(&s).ok());
+        let limit = if first { self.signature_limit } else { SIGNATURE_PAGE };
+
+        let mut signatures = Vec::new();
+        let mut before = None;
+        loop {
+            let config = GetConfirmedSignaturesForAddress2Config {
+                before,
+                until,
+                limit: Some(limit),
+                commitment: Some(self.client.commitment()),
+            };
+            let page = self
+                .client
+                .get_signatures_for_address_with_config(&pubkey, config)
+                .await
+                .map_err(|e| rpc_error(&url, e))?;
+            let full = page.len() == limit;
+            signatures.extend(page.into_iter().map(|status| status.signature));
+            if first || !full {
+                break;
+            }
+            before = match signatures.last().map(|s| Signature::from_str(s)) {
+                Some(Ok(oldest)) => Some(oldest),
+                Some(Err(e)) => return Err(FeedError::parse(&url, e)),
+                None => break,
+            };
+        }
+        signatures.reverse();
+        Ok(signatures)
+    }
+
+    async fn inspect(&self, signature: String) -> Result<Option<Article>, StoryError> {
+        let url = format!("{}/tx/{}", EXPLORER_URL, signature);
+        let params = json!([
+            signature,
+            {
+                "encoding": "jsonParsed",
+                "maxSupportedTransactionVersion": 0,
+                "commitment": self.client.commitment().commitment.to_string(),
+            }
+        ]);
+        let transaction: Value = self
+            .client
+            .send(RpcRequest::GetTransaction, params)
+            .await
+            .map_err(|e| StoryError::new(&url, rpc_error(&url, e)))?;
+        if transaction.is_null() {
+            return Err(StoryError::new(&url, FeedError::parse(&url, "transaction not found")));
+        }
+
+        let events = self.events(&transaction);
+        Ok((!events.is_empty()).then(|| self.article(&signature, &url, &transaction, events)))
+    }
+
+    fn events(&self, transaction: &Value) -> Vec<OnChainEvent> {
+        let meta = &transaction["meta"];
+        let keys = account_keys(transaction);
+        let mut events = Vec::new();
+
+        if !meta["err"].is_null() {
+            for program in self.programs.iter().filter(|program| keys.contains(program)) {
+                let logs: Vec<String> = meta["logMessages"]
+                    .as_array()
+                    .map(|logs| logs.iter().filter_map(Value::as_str).map(str::to_string).collect())
+                    .unwrap_or_default();
+                events.push(OnChainEvent::ProgramError {
+                    program: program.clone(),
+                    error: meta["err"].clone(),
+                    logs: logs[logs.len().saturating_sub(ERROR_LOG_LINES)..].to_vec(),
+                });
+            }
+            // Balances of a failed transaction only change by the fee.
+            return events;
+        }
+
+        if let Some(event) = self.sol_transfer(meta, &keys) {
+            events.push(event);
+        }
+
+        let mut minted_or_burned = HashSet::new();
+        for instruction in instructions(transaction) {
+            let parsed = &instruction["parsed"];
+            let info = &parsed["info"];
+            let Some(mint) = info["mint"].as_str() else {
+                continue;
+            };
+            let Some(token) = self.tokens.get(mint) else {
+                continue;
+            };
+            let kind = parsed["type"].as_str().unwrap_or_default();
+            if !matches!(kind, "mintTo" | "mintToChecked" | "burn" | "burnChecked") {
+                continue;
+            }
+            let amount = token_amount(info, mint, meta);
+            minted_or_burned.insert(mint.to_string());
+            if amount.is_none_or(|amount| amount < token.min_amount) {
+                continue;
+            }
+            let (mint, symbol, amount) = (mint.to_string(), Some(token.symbol.clone()), amount.unwrap_or_default());
+            events.push(if kind.starts_with("mint") {
+                OnChainEvent::Mint { mint, symbol, amount }
+            } else {
+                OnChainEvent::Burn { mint, symbol, amount }
+            });
+        }
+
+        for (mint, token) in &self.tokens {
+            if minted_or_burned.contains(mint) {
+                continue;
+            }
+            if let Some((from, to, amount)) = largest_token_move(meta, mint) {
+                if amount >= token.min_amount {
+                    events.push(OnChainEvent::TokenTransfer {
+                        mint: mint.clone(),
+                        symbol: Some(token.symbol.clone()),
+                        from,
+                        to,
+                        amount,
+                    });
+                }
+            }
+        }
+        events
+    }
+
+    /// The largest SOL balance increase, paired with the largest decrease.
+    fn sol_transfer(&self, meta: &Value, keys: &[String]) -> Option<OnChainEvent> {
+        let deltas: Vec<(usize, i128)> = meta["preBalances"]
+            .as_array()?
+            .iter()
+            .zip(meta["postBalances"].as_array()?)
+            .enumerate()
+            .map(|(index, (pre, post))| {
+                (index, post.as_u64().unwrap_or_default() as i128 - pre.as_u64().unwrap_or_default() as i128)
+            })
+            .collect();
+        let (to, received) = deltas.iter().copied().max_by_key(|(_, delta)| *delta)?;
+        let amount = received as f64 / LAMPORTS_PER_SOL as f64;
+        if received <= 0 || amount < self.min_sol_transfer {
+            return None;
+        }
+        let from = deltas.iter().copied().min_by_key(|(_, delta)| *delta).filter(|(_, delta)| *delta < 0);
+        Some(OnChainEvent::SolTransfer {
+            from: from.and_then(|(index, _)| keys.get(index).cloned()),
+            to: keys.get(to).cloned(),
+            amount,
+        })
+    }
+
+    fn article(&self, signature: &str, url: &str, transaction: &Value, events: Vec<OnChainEvent>) -> Article {
+        let published_at = transaction["blockTime"]
+            .as_i64()
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0));
+        let headlines: Vec<String> = events.iter().map(OnChainEvent::headline).collect();
+
+        let mut tags = vec!["onchain".to_string()];
+        let mut tickers = Vec::new();
+        for event in &events {
+            if !tags.iter().any(|tag| tag == event.tag()) {
+                tags.push(event.tag().to_string());
+            }
+            if let Some(ticker) = event.ticker() {
+                if !tickers.iter().any(|t| t == ticker) {
+                    tickers.push(ticker.to_string());
+                }
+            }
+        }
+
+        let mut body = headlines.join("\n");
+        for event in &events {
+            if let OnChainEvent::ProgramError { logs, .. } = event {
+                body.push_str("\n\n");
+                body.push_str(&logs.join("\n"));
+            }
+        }
+
+        let mut metadata = BTreeMap::new();
+        metadata.insert("signature".to_string(), Value::String(signature.to_string()));
+        metadata.insert("slot".to_string(), transaction["slot"].clone());
+        metadata.insert("events".to_string(), serde_json::to_value(&events).unwrap_or_default());
+
+        let mut article = Article {
+            id: article_id(url),
+            title: headlines[0].clone(),
+            author: String::new(),
+            body,
+            summary: None,
+            body_status: BodyStatus::Full,
+            url: url.to_string(),
+            source: "Solana".to_string(),
+            origin: None,
+            fetched_at: Some(Utc::now()),
+            language: None,
+            tags,
+            tickers,
+            metadata,
+            ..Default::default()
+        };
+        // `blockTime` is null when the node does not know when the block was produced.
+        article.set_published_at(published_at);
+        article
+    }
+}
+
+#[async_trait]
+impl Feed for SolanaFeed {
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        Ok(self.fetch_new_articles().await?.articles)
+    }
+
+    async fn fetch_report(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        self.fetch_new_articles().await
+    }
+}
+
+/// Sorts transport failures from the ones that will not go away by asking again.
+/// Timeouts are reported as [`RPC_TIMEOUT`], which is also the Solana client's default.
+fn rpc_error(url: &str, error: ClientError) -> FeedError {
+    let url = url.to_string();
+    match error.kind {
+        ClientErrorKind::Reqwest(e) => match e.status() {
+            Some(status) if status.as_u16() == 429 => FeedError::RateLimited { url, retry_after: None },
+            Some(status) => FeedError::Status {
+                url,
+                status: StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            },
+            None if e.is_timeout() => FeedError::TimedOut { url, after: RPC_TIMEOUT },
+            None => FeedError::Unreachable { url, message: e.to_string() },
+        },
+        ClientErrorKind::Io(e) => FeedError::Unreachable { url, message: e.to_string() },
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(message)) => FeedError::Unreachable { url, message },
+        kind => FeedError::parse(&url, kind),
+    }
+}
+
+fn address_url(address: &str) -> String {
+    format!("{}/address/{}", EXPLORER_URL, address)
+}
+
+/// Account addresses in the order balances refer to them, including ones loaded from
+/// lookup tables.
+fn account_keys(transaction: &Value) -> Vec<String> {
+    transaction["transaction"]["message"]["accountKeys"]
+        .as_array()
+        .map(|keys| {
+            keys.iter()
+                .filter_map(|key| key["pubkey"].as_str().or(key.as_str()))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Top-level and inner instructions.
+fn instructions(transaction: &Value) -> Vec<&Value> {
+    let mut instructions: Vec<&Value> = transaction["transaction"]["message"]["instructions"]
+        .as_array()
+        .map(|list| list.iter().collect())
+        .unwrap_or_default();
+    if let Some(inner) = transaction["meta"]["innerInstructions"].as_array() {
+        for group in inner {
+            if let Some(list) = group["instructions"].as_array() {
+                instructions.extend(list);
+            }
+        }
+    }
+    instructions
+}
+
+/// Amount of a parsed mint or burn instruction in whole tokens. Unchecked instructions
+/// carry raw units, so the decimals come from the transaction's token balances.
+fn token_amount(info: &Value, mint: &str, meta: &Value) -> Option<f64> {
+    if let Some(amount) = info["tokenAmount"]["uiAmountString"].as_str() {
+        return amount.parse().ok();
+    }
+    let raw: f64 = info["amount"].as_str()?.parse().ok()?;
+    let decimals = ["postTokenBalances", "preTokenBalances"]
+        .iter()
+        .filter_map(|key| meta[*key].as_array())
+        .flatten()
+        .find(|balance| balance["mint"] == mint)
+        .and_then(|balance| balance["uiTokenAmount"]["decimals"].as_i64())?;
+    Some(raw / 10f64.powi(decimals as i32))
+}
+
+/// Owner receiving the most of `mint`, the owner losing the most, and the amount
+/// received in whole tokens.
+fn largest_token_move(meta: &Value, mint: &str) -> Option<(Option<String>, Option<String>, f64)> {
+    let mut deltas: HashMap<String, f64> = HashMap::new();
+    for (key, sign) in [("preTokenBalances", -1.0), ("postTokenBalances", 1.0)] {
+        for balance in meta[key].as_array().into_iter().flatten().filter(|b| b["mint"] == mint) {
+            let owner = balance["owner"].as_str().unwrap_or_default().to_string();
+            let amount: f64 = balance["uiTokenAmount"]["uiAmountString"]
+                .as_str()
+                .and_then(|amount| amount.parse().ok())
+                .unwrap_or_default();
+            *deltas.entry(owner).or_default() += sign * amount;
+        }
+    }
+    let (to, received) = deltas.iter().max_by(|a, b| a.1.total_cmp(b.1))?;
+    if *received <= 0.0 {
+        return None;
+    }
+    let from = deltas.iter().filter(|(_, delta)| **delta < 0.0).min_by(|a, b| a.1.total_cmp(b.1));
+    let owner = |owner: &String| (!owner.is_empty()).then(|| owner.clone());
+    Some((from.and_then(|(o, _)| owner(o)), owner(to), *received))
+}
+
+/// Run against a JSON-RPC fixture server, except `test_validator_airdrop`, which is
+/// ignored by default and needs the node in `SOLANA_RPC_URL`, e.g.
+/// `http://127.0.0.1:8899` for `solana-test-validator`:
+/// `SOLANA_RPC_URL=... cargo test -- --ignored test_validator_airdrop`.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feeds::test_util::{respond, serve};
+
+    /// Signatures per address, newest first, and what fails.
+    #[derive(Default)]
+    struct Chain {
+        signatures: HashMap<String, Vec<String>>,
+        missing: HashSet<String>,
+        broken: HashSet<String>,
+        signature_requests: usize,
+    }
+
+    impl Chain {
+        fn handle(&mut self, request: &Value) -> Value {
+            let params = &request["params"];
+            let result = match request["method"].as_str().unwrap_or_default() {
+                "getSignaturesForAddress" => {
+                    self.signature_requests += 1;
+                    let address = params[0].as_str().unwrap_or_default();
+                    if self.broken.contains(address) {
+                        return json!({"jsonrpc": "2.0", "id": request["id"], "error": {"code": -32005, "message": "node is unhealthy"}});
+                    }
+                    let config = &params[1];
+                    let signatures = self.signatures.get(address).cloned().unwrap_or_default();
+                    let start = match config["before"].as_str() {
+                        Some(before) => signatures.iter().position(|s| s == before).map_or(signatures.len(), |i| i + 1),
+                        None => 0,
+                    };
+                    let page: Vec<Value> = signatures[start..]
+                        .iter()
+                        .take_while(|s| config["until"].as_str() != Some(s.as_str()))
+                        .take(config["limit"].as_u64().unwrap_or(1000) as usize)
+                        .map(|s| json!({"signature": s, "slot": 1, "err": null, "memo": null, "blockTime": 1734350000}))
+                        .collect();
+                    Value::Array(page)
+                }
+                "getTransaction" => {
+                    let signature = params[0].as_str().unwrap_or_default();
+                    if self.missing.contains(signature) {
+                        Value::Null
+                    } else {
+                        transfer()
+                    }
+                }
+                method => panic!("unexpected {}", method),
+            };
+            json!({"jsonrpc": "2.0", "id": request["id"], "result": result})
+        }
+
+        /// Adds `count` signatures to `address` and returns them, oldest first.
+        fn push(&mut self, address: &str, count: usize) -> Vec<String> {
+            let signatures = self.signatures.entry(address.to_string()).or_default();
+            let added: Vec<String> = (0..count).map(|_| signature()).collect();
+            for signature in &added {
+                signatures.insert(0, signature.clone());
+            }
+            added
+        }
+    }
+
+    fn signature() -> String {
+        let mut bytes = [0; 64];
+        bytes[..32].copy_from_slice(&Pubkey::new_unique().to_bytes());
+        Signature::from(bytes).to_string()
+    }
+
+    /// 100 SOL from one account to another.
+    fn transfer() -> Value {
+        json!({
+            "slot": 1,
+            "blockTime": 1734350000,
+            "meta": {
+                "err": null,
+                "preBalances": [100 * LAMPORTS_PER_SOL, 0],
+                "postBalances": [0, 100 * LAMPORTS_PER_SOL],
+                "preTokenBalances": [],
+                "postTokenBalances": [],
+            },
+            "transaction": {"message": {"accountKeys": ["Sender1111111111111", "Receiver11111111111"], "instructions": []}},
+        })
+    }
+
+    async fn rpc_server(chain: Arc<Mutex<Chain>>) -> String {
+        serve(move |request| {
+            let request: Value = serde_json::from_str(request.body()).unwrap();
+            let response = chain.lock().unwrap().handle(&request).to_string();
+            respond(200, &[("content-type", "application/json")], &response)
+        })
+        .await
+    }
+
+    async fn feed(chain: &Arc<Mutex<Chain>>, accounts: &[&str]) -> SolanaFeed {
+        let mut feed = SolanaFeed::new(&rpc_server(chain.clone()).await)
+            .with_min_sol_transfer(1.0)
+            .with_signature_limit(2)
+            .with_concurrency(16);
+        for account in accounts {
+            feed = feed.with_account(account);
+        }
+        feed
+    }
+
+    fn signatures(report: &FetchReport) -> HashSet<String> {
+        report.articles.iter().map(|a| a.metadata["signature"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn pages_back_to_the_previous_poll() {
+        let account = Pubkey::new_unique().to_string();
+        let chain = Arc::new(Mutex::new(Chain::default()));
+        let first = chain.lock().unwrap().push(&account, 3);
+        let feed = feed(&chain, &[&account]).await;
+
+        // The first poll only looks at the latest transactions.
+        let report = feed.fetch_new_articles().await.unwrap();
+        assert_eq!(signatures(&report), first[1..].iter().cloned().collect());
+
+        // More than a page since then: all of them, none twice.
+        let second = chain.lock().unwrap().push(&account, SIGNATURE_PAGE + 5);
+        chain.lock().unwrap().signature_requests = 0;
+        let report = feed.fetch_new_articles().await.unwrap();
+        assert!(report.failures.is_empty());
+        assert_eq!(signatures(&report), second.iter().cloned().collect());
+        assert_eq!(chain.lock().unwrap().signature_requests, 2);
+
+        assert!(feed.fetch_new_articles().await.unwrap().articles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn failed_transactions_are_tried_again() {
+        let account = Pubkey::new_unique().to_string();
+        let chain = Arc::new(Mutex::new(Chain::default()));
+        let feed = feed(&chain, &[&account]).await;
+        let old = chain.lock().unwrap().push(&account, 1);
+        assert_eq!(signatures(&feed.fetch_new_articles().await.unwrap()), HashSet::from([old[0].clone()]));
+
+        let added = chain.lock().unwrap().push(&account, 3);
+        chain.lock().unwrap().missing.insert(added[1].clone());
+        let report = feed.fetch_new_articles().await.unwrap();
+        assert_eq!(signatures(&report), HashSet::from([added[0].clone(), added[2].clone()]));
+        assert_eq!(report.failures.len(), 1);
+        assert!(report.failures[0].url.ends_with(&added[1]));
+
+        chain.lock().unwrap().missing.clear();
+        let report = feed.fetch_new_articles().await.unwrap();
+        assert_eq!(signatures(&report), HashSet::from([added[1].clone()]));
+        assert!(report.failures.is_empty());
+
+        assert!(feed.fetch_new_articles().await.unwrap().articles.is_empty());
+    }
+
+    #[tokio::test]
+    async fn oldest_failure_on_first_poll_does_not_crawl_history() {
+        let account = Pubkey::new_unique().to_string();
+        let chain = Arc::new(Mutex::new(Chain::default()));
+        chain.lock().unwrap().push(&account, 5);
+        let listed = chain.lock().unwrap().push(&account, 2);
+        chain.lock().unwrap().missing.insert(listed[0].clone());
+        let feed = feed(&chain, &[&account]).await;
+
+        let report = feed.fetch_new_articles().await.unwrap();
+        assert_eq!(signatures(&report), HashSet::from([listed[1].clone()]));
+        assert_eq!(report.failures.len(), 1);
+
+        chain.lock().unwrap().signature_requests = 0;
+        let added = chain.lock().unwrap().push(&account, 3);
+        let report = feed.fetch_new_articles().await.unwrap();
+        assert_eq!(signatures(&report), added.iter().cloned().collect());
+        assert!(report.failures.is_empty());
+        assert_eq!(chain.lock().unwrap().signature_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn failing_address_does_not_hide_the_others() {
+        let (healthy, broken) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+        let chain = Arc::new(Mutex::new(Chain::default()));
+        let added = chain.lock().unwrap().push(&healthy, 1);
+        chain.lock().unwrap().push(&broken, 1);
+        chain.lock().unwrap().broken.insert(broken.clone());
+
+        let report = feed(&chain, &[&broken, &healthy]).await.fetch_new_articles().await.unwrap();
+        assert_eq!(signatures(&report), HashSet::from([added[0].clone()]));
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].url, address_url(&broken));
+
+        let error = feed(&chain, &[&broken]).await.fetch_new_articles().await.unwrap_err();
+        assert!(error.downcast_ref::<FeedError>().is_some());
+    }
+
+    #[tokio::test]
+    async fn rpc_failures_keep_their_kind() {
+        let account = Pubkey::new_unique().to_string();
+        for (status, retry_after) in [(429, "0"), (503, "")] {
+            let rpc_url = serve(move |_| respond(status, &[("retry-after", retry_after)], "")).await;
+            let error = SolanaFeed::new(&rpc_url).with_account(&account).fetch_new_articles().await.unwrap_err();
+            let error = error.downcast_ref::<FeedError>().unwrap();
+            assert!(error.is_transient(), "{:?}", error);
+            assert_eq!(matches!(error, FeedError::RateLimited { .. }), status == 429);
+        }
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let rpc_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let error = SolanaFeed::new(&rpc_url).with_account(&account).fetch_new_articles().await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<FeedError>(), Some(FeedError::Unreachable { .. })));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a node in SOLANA_RPC_URL, e.g. solana-test-validator"]
+    async fn test_validator_airdrop() {
+        let rpc_url = std::env::var("SOLANA_RPC_URL").expect("SOLANA_RPC_URL");
+        let account = Pubkey::new_unique();
+        let feed = SolanaFeed::new(&rpc_url).with_account(&account.to_string()).with_min_sol_transfer(1.0);
+        assert!(feed.fetch_new_articles().await.unwrap().articles.is_empty());
+
+        let signature = feed.client().request_airdrop(&account, 5 * LAMPORTS_PER_SOL).await.unwrap();
+        feed.client().poll_for_signature(&signature).await.unwrap();
+
+        let report = feed.fetch_new_articles().await.unwrap();
+        assert!(report.failures.is_empty());
+        assert_eq!(report.articles.len(), 1);
+        assert_eq!(report.articles[0].metadata["signature"], signature.to_string());
+        let event = &report.articles[0].metadata["events"][0];
+        assert_eq!(event["kind"], "sol_transfer");
+        assert_eq!(event["to"], account.to_string());
+        assert_eq!(event["amount"], 5.0);
+    }
+}
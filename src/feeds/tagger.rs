@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::trader::coins::COINS;
+use super::base::{Article, Feed, FetchReport};
+
+/// Names the tokens in [`COINS`] go by besides their symbol.
+const DEFAULT_ALIASES: &[(&str, &[&str])] = &[
+    ("BTC", &["bitcoin", "XBT", "spot ETF", "spot bitcoin ETF"]),
+    ("WBTC", &["wrapped bitcoin"]),
+    ("SOL", &["solana"]),
+    ("USDC", &["USD Coin"]),
+];
+
+/// Key in [`Article::metadata`] under which the matches are stored.
+pub const ENTITIES_KEY: &str = "entities";
+
+/// Which part of the article a match was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextField {
+    Title,
+    Body,
+}
+
+/// An asset mentioned in an article. `start` and `end` are byte offsets into the field.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntityMatch {
+    pub symbol: String,
+    /// The text as it appears in the article.
+    pub text: String,
+    pub field: TextField,
+    pub start: usize,
+    pub end: usize,
+}
+
+struct Alias {
+    symbol: String,
+    alias: String,
+    /// All-caps aliases are tickers and only match as written, so "SOL" does not
+    /// match "sol" in a Spanish headline.
+    case_sensitive: bool,
+}
+
+/// Finds the assets an article is about by looking for their symbols, names and
+/// aliases in the title and body. Matches must be whole words; where aliases overlap
+/// the longest wins, so "wrapped bitcoin" is WBTC rather than BTC.
+pub struct EntityTagger {
+    aliases: Vec<Alias>,
+}
+
+impl Default for EntityTagger {
+    /// Every token in [`COINS`] under its symbol and the built-in aliases.
+    fn default() -> Self {
+        let mut tagger = Self::new();
+        let mut symbols: Vec<&str> = COINS.keys().copied().collect();
+        symbols.sort();
+        for symbol in symbols {
+            tagger = tagger.with_alias(symbol, symbol);
+            for (_, aliases) in DEFAULT_ALIASES.iter().filter(|(s, _)| *s == symbol) {
+                tagger = tagger.with_aliases(symbol, aliases);
+            }
+        }
+        tagger
+    }
+}
+
+impl EntityTagger {
+    /// A tagger without any aliases.
+    pub fn new() -> Self {
+        Self { aliases: Vec::new() }
+    }
+
+    /// Builds a tagger from a table of symbol to aliases. The symbol itself is
+    /// always matched too.
+    pub fn from_table(table: &HashMap<String, Vec<String>>) -> Self {
+        let mut symbols: Vec<&String> = table.keys().collect();
+        symbols.sort();
+        let mut tagger = Self::new();
+        for symbol in symbols {
+            tagger = tagger.with_alias(symbol, symbol);
+            for alias in &table[symbol] {
+                tagger = tagger.with_alias(symbol, alias);
+            }
+        }
+        tagger
+    }
+
+    /// Reads the table from a JSON file such as `{"BTC": ["bitcoin", "XBT"]}`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let table: HashMap<String, Vec<String>> = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self::from_table(&table))
+    }
+
+    pub fn with_alias(mut self, symbol: &str, alias: &str) -> Self {
+        let alias = alias.trim();
+        let duplicate = self.aliases.iter().any(|a| a.symbol == symbol && a.alias == alias);
+        if !alias.is_empty() && !duplicate {
+            self.aliases.push(Alias {
+                symbol: symbol.to_string(),
+                alias: alias.to_string(),
+                case_sensitive: alias.chars().any(char::is_alphabetic)
+                    && !alias.chars().any(char::is_lowercase),
+            });
+        }
+        self
+    }
+
+    pub fn with_aliases(mut self, symbol: &str, aliases: &[&str]) -> Self {
+        for alias in aliases {
+            self = self.with_alias(symbol, alias);
+        }
+        self
+    }
+
+    /// All asset mentions in the title, then the body, in order of appearance.
+    pub fn matches(&self, article: &Article) -> Vec<EntityMatch> {
+        let mut matches = self.find(&article.title, TextField::Title);
+        matches.extend(self.find(&article.body, TextField::Body));
+        matches
+    }
+
+    /// Adds the matched symbols to `article.tickers` and the matches to
+    /// `article.metadata["entities"]`. Returns the matches.
+    pub fn tag(&self, article: &mut Article) -> Vec<EntityMatch> {
+        let matches = self.matches(article);
+        for m in &matches {
+            if !article.tickers.contains(&m.symbol) {
+                article.tickers.push(m.symbol.clone());
+            }
+        }
+        if let Ok(value) = serde_json::to_value(&matches) {
+            article.metadata.insert(ENTITIES_KEY.to_string(), value);
+        }
+        matches
+    }
+
+    fn find(&self, text: &str, field: TextField) -> Vec<EntityMatch> {
+        let bytes = text.as_bytes();
+        let mut candidates = Vec::new();
+        for (start, _) in text.char_indices() {
+            if !is_boundary(text[..start].chars().next_back()) {
+                continue;
+            }
+            for alias in &self.aliases {
+                let end = start + alias.alias.len();
+                let Some(window) = bytes.get(start..end) else {
+                    continue;
+                };
+                let found = if alias.case_sensitive {
+                    window == alias.alias.as_bytes()
+                } else {
+                    window.eq_ignore_ascii_case(alias.alias.as_bytes())
+                };
+                if found && text.is_char_boundary(end) && is_boundary(text[end..].chars().next()) {
+                    candidates.push((start, end, alias));
+                }
+            }
+        }
+
+        // Longest match first at each position, then skip anything overlapping a kept match.
+        candidates.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+        let mut matches: Vec<EntityMatch> = Vec::new();
+        for (start, end, alias) in candidates {
+            if matches.last().is_some_and(|last| start < last.end) {
+                continue;
+            }
+            matches.push(EntityMatch {
+                symbol: alias.symbol.clone(),
+                text: text[start..end].to_string(),
+                field,
+                start,
+                end,
+            });
+        }
+        matches
+    }
+}
+
+/// Whether the character next to a match ends a word. `$BTC` and `#bitcoin` still count.
+fn is_boundary(c: Option<char>) -> bool {
+    c.is_none_or(|c| !c.is_alphanumeric() && c != '_')
+}
+
+/// The matches [`EntityTagger::tag`] stored on an article.
+pub fn entities(article: &Article) -> Vec<EntityMatch> {
+    article
+        .metadata
+        .get(ENTITIES_KEY)
+        .cloned()
+        .and_then(|value: Value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// Wraps a feed so every article comes tagged with the assets it mentions.
+pub struct TaggedFeed<F> {
+    feed: F,
+    tagger: EntityTagger,
+    only_tagged: bool,
+}
+
+impl<F: Feed> TaggedFeed<F> {
+    pub fn new(feed: F, tagger: EntityTagger) -> Self {
+        Self { feed, tagger, only_tagged: false }
+    }
+
+    /// Drops articles that mention none of the tagger's assets.
+    pub fn with_only_tagged(mut self, only_tagged: bool) -> Self {
+        self.only_tagged = only_tagged;
+        self
+    }
+
+    pub fn feed(&self) -> &F {
+        &self.feed
+    }
+
+    pub fn tagger(&self) -> &EntityTagger {
+        &self.tagger
+    }
+}
+
+#[async_trait]
+impl<F: Feed> Feed for TaggedFeed<F> {
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        Ok(self.fetch_report().await?.articles)
+    }
+
+    async fn fetch_report(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        let mut report = self.feed.fetch_report().await?;
+        report.articles.retain_mut(|article| {
+            let tagged = !self.tagger.tag(article).is_empty();
+            tagged || !self.only_tagged
+        });
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tagger() -> EntityTagger {
+        EntityTagger::new()
+            .with_aliases("BTC", &["BTC", "bitcoin"])
+            .with_aliases("WBTC", &["WBTC", "wrapped bitcoin"])
+            .with_aliases("SOL", &["SOL", "solana"])
+    }
+
+    fn article(title: &str, body: &str) -> Article {
+        Article { title: title.to_string(), body: body.to_string(), ..Default::default() }
+    }
+
+    fn spans(matches: &[EntityMatch]) -> Vec<(&str, TextField, usize, usize)> {
+        matches.iter().map(|m| (m.symbol.as_str(), m.field, m.start, m.end)).collect()
+    }
+
+    #[test]
+    fn spans_are_byte_offsets_into_each_field() {
+        // "è" takes two bytes.
+        let article = article("Bitcoin tops $100k", "Après le rally, $BTC et Solana montent.");
+        let matches = tagger().matches(&article);
+        assert_eq!(
+            spans(&matches),
+            [("BTC", TextField::Title, 0, 7), ("BTC", TextField::Body, 18, 21), ("SOL", TextField::Body, 25, 31)]
+        );
+        assert_eq!(matches[0].text, "Bitcoin");
+        assert_eq!(&article.body[matches[2].start..matches[2].end], "Solana");
+    }
+
+    #[test]
+    fn longest_whole_word_wins() {
+        let matches = tagger().matches(&article("Wrapped Bitcoin supply grows", "bitcoiners and BTCUSD aside, btc is not BTC."));
+        assert_eq!(spans(&matches), [("WBTC", TextField::Title, 0, 15), ("BTC", TextField::Body, 40, 43)]);
+        // Tickers are case-sensitive, names are not.
+        assert!(tagger().matches(&article("El sol sale", "")).is_empty());
+        assert_eq!(tagger().matches(&article("SOLANA rallies", ""))[0].symbol, "SOL");
+    }
+
+    #[test]
+    fn tag_stores_matches_on_the_article() {
+        let mut article = article("Bitcoin and Solana", "Bitcoin again.");
+        let matches = tagger().tag(&mut article);
+        assert_eq!(article.tickers, ["BTC", "SOL"]);
+        assert_eq!(entities(&article), matches);
+        assert_eq!(matches.len(), 3);
+    }
+}
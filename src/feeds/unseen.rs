@@ -0,0 +1,168 @@
+use async_trait::async_trait;
+use crate::db::base::{ArticleStore, RecordOutcome};
+use super::base::{Article, Feed, FetchReport};
+
+/// Wraps a feed so that only articles the store has not seen before are yielded, along
+/// with new versions of seen ones, e.g. the full story after a headline-only article or
+/// an updated story. Every delivered article is recorded, which keeps the filter working
+/// across restarts.
+pub struct UnseenFeed<F, S> {
+    feed: F,
+    store: S,
+    include_changed: bool,
+}
+
+impl<F: Feed, S: ArticleStore> UnseenFeed<F, S> {
+    pub fn new(feed: F, store: S) -> Self {
+        Self { feed, store, include_changed: true }
+    }
+
+    /// Whether articles whose URL was seen before but whose title or body changed are
+    /// yielded again. On by default; without it only the first version of a story is.
+    pub fn with_changed(mut self, include_changed: bool) -> Self {
+        self.include_changed = include_changed;
+        self
+    }
+
+    pub fn feed(&self) -> &F {
+        &self.feed
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+}
+
+#[async_trait]
+impl<F: Feed, S: ArticleStore> Feed for UnseenFeed<F, S> {
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        Ok(self.fetch_report().await?.articles)
+    }
+
+    /// Failed stories are passed on as they are; they were never recorded.
+    async fn fetch_report(&self) -> Result<FetchReport, Box<dyn std::error::Error>> {
+        let report = self.feed.fetch_report().await?;
+
+        let mut unseen = Vec::new();
+        for article in report.articles {
+            let outcome = self.store.record(&article).await?;
+            match outcome {
+                RecordOutcome::New => unseen.push(article),
+                RecordOutcome::Changed if self.include_changed => unseen.push(article),
+                _ => {}
+            }
+        }
+        Ok(FetchReport { articles: unseen, failures: report.failures })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+    use serde_json::Value;
+    use crate::db::sqlite::SqliteStore;
+    use crate::feeds::error::FeedError;
+    use crate::feeds::multi::MultiFeed;
+    use super::*;
+
+    /// Returns the next batch on every poll.
+    struct Batches(Mutex<VecDeque<Vec<Article>>>);
+
+    #[async_trait]
+    impl Feed for Batches {
+        async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+            Ok(self.0.lock().unwrap().pop_front().unwrap_or_default())
+        }
+    }
+
+    impl Batches {
+        fn new(batches: Vec<Vec<Article>>) -> Self {
+            Self(Mutex::new(batches.into()))
+        }
+    }
+
+    struct Failing;
+
+    #[async_trait]
+    impl Feed for Failing {
+        async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+            Err(FeedError::Paywalled { url: "https://example.com/story".to_string() }.into())
+        }
+    }
+
+    fn article(url: &str) -> Article {
+        Article {
+            title: format!("Story at {}", url),
+            url: url.to_string(),
+            published_at: "2024-12-05T02:42:11Z".parse().unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn story(body: &str, updated_at: &str) -> Article {
+        let mut article = Article {
+            title: "Bitcoin Tops $100,000 for First Time".to_string(),
+            body: body.to_string(),
+            url: "https://www.bloomberg.com/news/articles/2024-12-05/bitcoin-tops-100-000".to_string(),
+            source: "Bloomberg".to_string(),
+            published_at: "2024-12-05T02:42:11Z".parse().unwrap(),
+            ..Default::default()
+        };
+        article.metadata.insert("updated_at".to_string(), Value::from(updated_at));
+        article
+    }
+
+    #[tokio::test]
+    async fn updated_story_passes_through_multi_and_unseen_feeds() {
+        let first = story("Bitcoin rose above $100,000.", "2024-12-05T02:42:11Z");
+        let updated = story("Bitcoin rose above $100,000, then pared gains.", "2024-12-05T03:10:00Z");
+        let batches = vec![vec![first.clone()], vec![first, updated.clone()], vec![updated]];
+        let multi = MultiFeed::new().with_feed("bloomberg", Batches::new(batches));
+        let feed = UnseenFeed::new(multi, SqliteStore::in_memory().await.unwrap());
+
+        let articles = feed.get_new_articles().await.unwrap();
+        assert_eq!(articles.len(), 1);
+
+        let articles = feed.get_new_articles().await.unwrap();
+        assert_eq!(articles.len(), 1);
+        assert_eq!(articles[0].body, "Bitcoin rose above $100,000, then pared gains.");
+        assert_eq!(articles[0].origin.as_deref(), Some("bloomberg"));
+
+        assert!(feed.get_new_articles().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn seen_articles_stay_seen_across_restarts() {
+        let store = Arc::new(SqliteStore::in_memory().await.unwrap());
+        let a = article("https://example.com/a");
+        let b = article("https://example.com/b");
+        let c = article("https://example.com/c");
+
+        let feed = UnseenFeed::new(Batches::new(vec![vec![a.clone(), b.clone()]]), store.clone());
+        assert_eq!(feed.get_new_articles().await.unwrap().len(), 2);
+
+        let restarted = UnseenFeed::new(Batches::new(vec![vec![a, b, c]]), store.clone());
+        let articles = restarted.get_new_articles().await.unwrap();
+        assert_eq!(articles.len(), 1);
+        assert_eq!(articles[0].url, "https://example.com/c");
+    }
+
+    #[tokio::test]
+    async fn changed_articles_can_be_left_out() {
+        let first = article("https://example.com/a");
+        let changed = Article { body: "Updated.".to_string(), ..first.clone() };
+        let store = SqliteStore::in_memory().await.unwrap();
+        let feed = UnseenFeed::new(Batches::new(vec![vec![first], vec![changed]]), store).with_changed(false);
+
+        assert_eq!(feed.get_new_articles().await.unwrap().len(), 1);
+        assert!(feed.get_new_articles().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn feed_errors_pass_through_unchanged() {
+        let feed = UnseenFeed::new(Failing, SqliteStore::in_memory().await.unwrap());
+        let error = feed.get_new_articles().await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<FeedError>(), Some(FeedError::Paywalled { .. })));
+    }
+}
@@ -0,0 +1,507 @@
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use async_trait::async_trait;
+use base64::prelude::*;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Bytes, Incoming};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::{TokioIo, TokioTimer};
+use serde_json::Value;
+use sha2::Sha256;
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use super::base::{article_id, Article, BodyStatus, Feed};
+use super::extract::parse_date;
+
+// Keys under which vendors wrap a batch of articles.
+const BATCH_KEYS: &[&str] = &["articles", "data", "items", "news", "stories", "results"];
+
+// Wait after a failed accept, doubling up to the maximum while failures persist.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// How incoming requests prove they come from the vendor.
+#[derive(Debug, Clone)]
+pub enum WebhookAuth {
+    /// Accepts every request. Only for listeners untrusted clients cannot reach.
+    None,
+    /// The header must carry exactly this secret, e.g. `Authorization: Bearer <secret>`.
+    SharedSecret { header: String, secret: String },
+    /// The header must carry the HMAC-SHA256 of the raw body, hex or base64 encoded,
+    /// optionally prefixed with `sha256=`.
+    HmacSha256 { header: String, secret: Vec<u8> },
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    bind: SocketAddr,
+    path: String,
+    auth: WebhookAuth,
+    source: Option<String>,
+    capacity: usize,
+    max_body_bytes: usize,
+    read_timeout: Duration,
+}
+
+impl WebhookConfig {
+    /// Listens on `bind`; port 0 picks a free port, see [`WebhookFeed::local_addr`].
+    /// Requests failing `auth` are refused with 401.
+    pub fn new(bind: SocketAddr, auth: WebhookAuth) -> Self {
+        Self {
+            bind,
+            path: "/articles".to_string(),
+            auth,
+            source: None,
+            capacity: 10_000,
+            max_body_bytes: 4 * 1024 * 1024,
+            read_timeout: Duration::from_secs(10),
+        }
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// Source set on articles whose payload does not name one.
+    pub fn source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_string());
+        self
+    }
+
+    /// Articles buffered before further deliveries are refused with 503, so the
+    /// vendor retries instead of the articles being dropped.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Time a client gets to send the request headers, and again to send the body.
+    /// Slower clients are disconnected, or answered with 408 once the headers are in.
+    pub fn read_timeout(mut self, read_timeout: Duration) -> Self {
+        self.read_timeout = read_timeout;
+        self
+    }
+}
+
+#[derive(Default)]
+struct Inbox {
+    articles: Mutex<VecDeque<Article>>,
+    arrived: Notify,
+}
+
+/// Embedded HTTP listener that buffers articles pushed by news vendors until the
+/// agent drains them through [`Feed::get_new_articles`] or [`WebhookFeed::recv`].
+///
+/// Accepts `POST` requests on the configured path carrying one article, an array of
+/// articles, or a vendor batch (`{"articles": [...]}`, `{"data": [...]}`, ...). Besides
+/// the `Article` JSON shape, common field names such as `headline`, `content`, `link`
+/// and `publishedAt` are understood.
+pub struct WebhookFeed {
+    inbox: Arc<Inbox>,
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl WebhookFeed {
+    pub async fn bind(config: WebhookConfig) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(config.bind).await?;
+        let local_addr = listener.local_addr()?;
+        let inbox = Arc::new(Inbox::default());
+        let config = Arc::new(config);
+
+        let task = {
+            let inbox = inbox.clone();
+            tokio::spawn(async move {
+                let mut backoff = ACCEPT_BACKOFF;
+                loop {
+                    // Errors such as EMFILE persist until connections close, so wait
+                    // instead of spinning on them.
+                    let stream = match listener.accept().await {
+                        Ok((stream, _)) => stream,
+                        Err(e) => {
+                            eprintln!("Webhook listener on {} could not accept a connection: {}", local_addr, e);
+                            tokio::time::sleep(backoff).await;
+                            backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+                            continue;
+                        }
+                    };
+                    backoff = ACCEPT_BACKOFF;
+                    let inbox = inbox.clone();
+                    let config = config.clone();
+                    tokio::spawn(async move {
+                        let read_timeout = config.read_timeout;
+                        let service = service_fn(move |request| handle(request, inbox.clone(), config.clone()));
+                        let _ = http1::Builder::new()
+                            .timer(TokioTimer::new())
+                            .header_read_timeout(read_timeout)
+                            .serve_connection(TokioIo::new(stream), service)
+                            .await;
+                    });
+                }
+            })
+        };
+
+        Ok(Self { inbox, local_addr, task })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Number of articles waiting to be drained.
+    pub fn pending(&self) -> usize {
+        self.inbox.articles.lock().unwrap().len()
+    }
+
+    /// Waits until at least one article has arrived and drains the buffer.
+    pub async fn recv(&self) -> Vec<Article> {
+        loop {
+            let arrived = self.inbox.arrived.notified();
+            let articles = self.drain();
+            if !articles.is_empty() {
+                return articles;
+            }
+            arrived.await;
+        }
+    }
+
+    fn drain(&self) -> Vec<Article> {
+        self.inbox.articles.lock().unwrap().drain(..).collect()
+    }
+}
+
+impl Drop for WebhookFeed {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle(
+    request: Request<Incoming>,
+    inbox: Arc<Inbox>,
+    config: Arc<WebhookConfig>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if request.uri().path() != config.path {
+        return Ok(respond(StatusCode::NOT_FOUND, "Not found"));
+    }
+    if request.method() != Method::POST {
+        return Ok(respond(StatusCode::METHOD_NOT_ALLOWED, "Only POST is accepted"));
+    }
+
+    let (parts, body) = request.into_parts();
+    let body = tokio::time::timeout(config.read_timeout, Limited::new(body, config.max_body_bytes).collect());
+    let body = match body.await {
+        Ok(Ok(body)) => body.to_bytes(),
+        Ok(Err(_)) => return Ok(respond(StatusCode::PAYLOAD_TOO_LARGE, "Body too large")),
+        Err(_) => return Ok(respond(StatusCode::REQUEST_TIMEOUT, "Body not received in time")),
+    };
+
+    if !authorized(&config.auth, &parts.headers, &body) {
+        return Ok(respond(StatusCode::UNAUTHORIZED, "Invalid signature"));
+    }
+
+    let articles = match parse_payload(&body, config.source.as_deref()) {
+        Ok(articles) => articles,
+        Err(message) => return Ok(respond(StatusCode::BAD_REQUEST, &message)),
+    };
+
+    let accepted = articles.len();
+    {
+        let mut buffer = inbox.articles.lock().unwrap();
+        if buffer.len() + accepted > config.capacity {
+            return Ok(respond(StatusCode::SERVICE_UNAVAILABLE, "Buffer full, retry later"));
+        }
+        buffer.extend(articles);
+    }
+    inbox.arrived.notify_waiters();
+
+    Ok(respond(StatusCode::ACCEPTED, &format!("{{\"accepted\":{}}}", accepted)))
+}
+
+fn respond(status: StatusCode, body: &str) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+    *response.status_mut() = status;
+    response
+}
+
+fn authorized(auth: &WebhookAuth, headers: &hyper::HeaderMap, body: &[u8]) -> bool {
+    let header_value = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(str::trim);
+    match auth {
+        WebhookAuth::None => true,
+        WebhookAuth::SharedSecret { header, secret } => header_value(header).is_some_and(|value| {
+            let value = value.strip_prefix("Bearer ").unwrap_or(value);
+            constant_time_eq(value.as_bytes(), secret.as_bytes())
+        }),
+        WebhookAuth::HmacSha256 { header, secret } => header_value(header).is_some_and(|value| {
+            let value = value.strip_prefix("sha256=").unwrap_or(value);
+            let Some(signature) = decode_hex(value).or_else(|| BASE64_STANDARD.decode(value).ok()) else {
+                return false;
+            };
+            let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret) else {
+                return false;
+            };
+            mac.update(body);
+            mac.verify_slice(&signature).is_ok()
+        }),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Turns a pushed payload into articles: a single object, an array, or an object
+/// wrapping an array under one of the usual batch keys.
+pub fn parse_payload(body: &[u8], source: Option<&str>) -> Result<Vec<Article>, String> {
+    let value: Value = serde_json::from_slice(body).map_err(|e| format!("Invalid JSON: {}", e))?;
+
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(ref map) => match BATCH_KEYS.iter().find_map(|key| map.get(*key).and_then(Value::as_array)) {
+            Some(items) => items.clone(),
+            None => vec![value],
+        },
+        _ => return Err("Expected a JSON object or array".to_string()),
+    };
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(index, item)| {
+            vendor_article(item, source).ok_or_else(|| format!("Item {} has no URL", index))
+        })
+        .collect()
+}
+
+fn vendor_article(item: Value, source: Option<&str>) -> Option<Article> {
+    // Vendors often nest the story itself, e.g. `{"content": {"title": ...}}`.
+    if let Some(inner) = item.get("content").filter(|c| c.is_object()) {
+        return vendor_article(inner.clone(), source);
+    }
+    if let Ok(mut article) = serde_json::from_value::<Article>(item.clone()) {
+        if article.source.is_empty() {
+            article.source = source.unwrap_or_default().to_string();
+        }
+        if article.url.is_empty() {
+            return None;
+        }
+        article.fetched_at.get_or_insert_with(Utc::now);
+        return Some(article);
+    }
+
+    let text = |keys: &[&str]| keys.iter().find_map(|key| name_or_string(&item[*key]));
+    // The URL is the article's identity; without one the agent cannot dedupe it.
+    let url = text(&["url", "link", "href", "permalink"])?;
+    let title = text(&["title", "headline", "name"]);
+
+    let body = text(&["body", "content", "text", "story"]);
+    let summary = text(&["summary", "description", "teaser", "abstract"]);
+    let published_at = ["published_at", "publishedAt", "published", "pubDate", "created_at", "createdAt", "created", "timestamp", "time", "date"]
+        .iter()
+        .find_map(|key| json_date(&item[*key]));
+
+    let (body, body_status) = match (body, &summary) {
+        (Some(body), _) => (body, BodyStatus::Full),
+        (None, Some(summary)) => (summary.clone(), BodyStatus::Truncated),
+        (None, None) => (String::new(), BodyStatus::Missing),
+    };
+
+    let list = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| string_list(&item[*key]))
+            .unwrap_or_default()
+    };
+
+    let mut article = Article {
+        id: article_id(&url),
+        title: title.unwrap_or_default(),
+        author: text(&["author", "byline", "creator", "authors"]).unwrap_or_default(),
+        body,
+        summary,
+        body_status,
+        url,
+        source: text(&["source", "publisher", "site"])
+            .or_else(|| source.map(str::to_string))
+            .unwrap_or_default(),
+        origin: None,
+        fetched_at: Some(Utc::now()),
+        language: text(&["language", "lang", "locale"]),
+        tags: list(&["tags", "categories", "keywords", "topics"]),
+        tickers: list(&["tickers", "symbols", "instruments"]),
+        ..Default::default()
+    };
+    article.set_published_at(published_at);
+    Some(article)
+}
+
+/// A string, an object with a `name`, or a list of those.
+fn name_or_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Object(map) => map.get("name").and_then(name_or_string),
+        Value::Array(values) => {
+            let names: Vec<String> = values.iter().filter_map(name_or_string).collect();
+            (!names.is_empty()).then(|| names.join(", "))
+        }
+        _ => None,
+    }
+}
+
+/// A list of strings or of objects with a `name` or `symbol`, or one comma-separated string.
+fn string_list(value: &Value) -> Option<Vec<String>> {
+    let values: Vec<String> = match value {
+        Value::String(s) => s.split(',').map(|v| v.trim().to_string()).collect(),
+        Value::Array(values) => values
+            .iter()
+            .filter_map(|v| match v {
+                Value::String(s) => Some(s.trim().to_string()),
+                Value::Object(map) => map.get("symbol").or(map.get("name")).and_then(Value::as_str).map(|s| s.trim().to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => return None,
+    };
+    let values: Vec<String> = values.into_iter().filter(|v| !v.is_empty()).collect();
+    (!values.is_empty()).then_some(values)
+}
+
+/// A date string, or a Unix timestamp in seconds or milliseconds.
+fn json_date(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::String(s) => parse_date(s),
+        Value::Number(n) => {
+            let n = n.as_i64()?;
+            if n > 100_000_000_000 {
+                DateTime::from_timestamp_millis(n)
+            } else {
+                DateTime::from_timestamp(n, 0)
+            }
+        }
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl Feed for WebhookFeed {
+    async fn get_new_articles(&self) -> Result<Vec<Article>, Box<dyn std::error::Error>> {
+        Ok(self.drain())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use super::*;
+
+    const SECRET: &[u8] = b"vendor-secret";
+    const PAYLOAD: &str = r#"{"articles": [{"headline": "Fed Holds Rates Steady", "link": "https://example.com/fed", "publishedAt": "2024-12-18T19:00:00Z"}]}"#;
+
+    async fn listener(read_timeout: Duration) -> WebhookFeed {
+        let auth = WebhookAuth::HmacSha256 { header: "x-signature".to_string(), secret: SECRET.to_vec() };
+        let config = WebhookConfig::new("127.0.0.1:0".parse().unwrap(), auth).read_timeout(read_timeout);
+        WebhookFeed::bind(config).await.unwrap()
+    }
+
+    fn signature(body: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET).unwrap();
+        mac.update(body.as_bytes());
+        let signature: String = mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect();
+        format!("sha256={}", signature)
+    }
+
+    #[tokio::test]
+    async fn accepts_signed_posts_only() {
+        let feed = listener(Duration::from_secs(5)).await;
+        let url = format!("http://{}/articles", feed.local_addr());
+        let client = reqwest::Client::new();
+
+        let unsigned = client.post(&url).body(PAYLOAD).send().await.unwrap();
+        assert_eq!(unsigned.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let forged = client.post(&url).header("x-signature", signature("{}")).body(PAYLOAD).send().await.unwrap();
+        assert_eq!(forged.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(feed.pending(), 0);
+
+        let signed = client.post(&url).header("x-signature", signature(PAYLOAD)).body(PAYLOAD).send().await.unwrap();
+        assert_eq!(signed.status(), reqwest::StatusCode::ACCEPTED);
+        let articles = feed.get_new_articles().await.unwrap();
+        assert_eq!(articles.len(), 1);
+        assert_eq!(articles[0].title, "Fed Holds Rates Steady");
+        assert_eq!(articles[0].url, "https://example.com/fed");
+    }
+
+    #[test]
+    fn undated_items_are_flagged() {
+        let dated = vendor_article(serde_json::json!({"headline": "Dated", "link": "https://example.com/dated", "publishedAt": "2024-12-18T19:00:00Z"}), None).unwrap();
+        assert!(!dated.published_at_is_estimated());
+        let undated = vendor_article(serde_json::json!({"headline": "Undated", "link": "https://example.com/undated"}), None).unwrap();
+        assert!(undated.published_at_is_estimated());
+        assert_eq!(Some(undated.published_at), undated.fetched_at);
+    }
+
+    #[tokio::test]
+    async fn items_without_a_url_are_rejected() {
+        let feed = listener(Duration::from_secs(5)).await;
+        let url = format!("http://{}/articles", feed.local_addr());
+        let client = reqwest::Client::new();
+
+        for body in [
+            r#"{"articles": [{"headline": "No Link"}]}"#,
+            r#"{"title": "No Link", "url": "", "published_at": "2024-12-18T19:00:00Z"}"#,
+        ] {
+            let response = client.post(&url).header("x-signature", signature(body)).body(body).send().await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST, "{}", body);
+        }
+        assert_eq!(feed.pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn slow_clients_time_out() {
+        let feed = listener(Duration::from_millis(200)).await;
+
+        // Headers that never finish: the connection is closed.
+        let mut stream = TcpStream::connect(feed.local_addr()).await.unwrap();
+        stream.write_all(b"POST /articles HTTP/1.1\r\nHost: 127.0.0.1\r\n").await.unwrap();
+        let mut response = Vec::new();
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut response)).await;
+        assert!(read.is_ok(), "connection still open");
+
+        // A body that never finishes: 408.
+        let mut stream = TcpStream::connect(feed.local_addr()).await.unwrap();
+        let head = format!(
+            "POST /articles HTTP/1.1\r\nHost: 127.0.0.1\r\nx-signature: {}\r\nContent-Length: {}\r\n\r\n",
+            signature(PAYLOAD),
+            PAYLOAD.len()
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        stream.write_all(&PAYLOAD.as_bytes()[..10]).await.unwrap();
+        let mut response = [0; 64];
+        let read = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut response)).await.unwrap().unwrap();
+        assert!(String::from_utf8_lossy(&response[..read]).starts_with("HTTP/1.1 408"));
+        assert_eq!(feed.pending(), 0);
+    }
+}
@@ -0,0 +1,34 @@
+use roxmltree::Node;
+
+/// First child element called `name`, in any namespace.
+pub(crate) fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+pub(crate) fn namespaced_child<'a, 'input>(node: Node<'a, 'input>, namespace: &str, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.is_element() && n.tag_name().namespace() == Some(namespace) && n.tag_name().name() == name)
+}
+
+/// Concatenates every text and CDATA node under `node`.
+pub(crate) fn text_of(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Text of the first child element called `name`, unless it is empty.
+pub(crate) fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).map(text_of).filter(|text| !text.is_empty())
+}
+
+/// Text of the first element called `name` anywhere under `node`, unless it is empty.
+pub(crate) fn descendant_text(node: Node, name: &str) -> Option<String> {
+    node.descendants()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+        .map(text_of)
+        .filter(|text| !text.is_empty())
+}
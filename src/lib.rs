@@ -2,4 +2,5 @@ pub mod feeds;
 pub mod terminal;
 pub mod agent;
 pub mod ai;
-pub mod trader;
\ No newline at end of file
+pub mod trader;
+pub mod db;
\ No newline at end of file